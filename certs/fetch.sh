@@ -0,0 +1,15 @@
+#!/bin/sh
+# Extracts the Cast root certificates from the openscreen headers, see README.md
+set -eu
+
+base="https://chromium.googlesource.com/openscreen/+/refs/heads/main/cast/common/certificate"
+cd "$(dirname "$0")"
+
+fetch() {
+    curl -sSf "$base/$1?format=TEXT" | base64 -d | grep -o '0x[0-9a-fA-F]\{2\}' \
+        | sed 's/0x//' | tr -d '\n' | xxd -r -p > "$2"
+    openssl x509 -inform der -in "$2" -noout -subject
+}
+
+fetch cast_root_ca_cert_der-inc.h cast_root_ca.der
+fetch eureka_root_ca_der-inc.h eureka_root_ca.der
@@ -0,0 +1,44 @@
+extern crate cast_sender;
+
+use std::time::Instant;
+
+use futures_util::future;
+use macro_rules_attribute::apply;
+use smol_macros::main;
+
+use cast_sender::mock::MockReceiver;
+use cast_sender::{Error, Receiver};
+
+// Number of requests which are in flight at the same time
+const CONCURRENCY: usize = 256;
+const ROUNDS: usize = 20;
+
+/// Measures the request throughput against the mock receiver,
+/// run with `cargo run --release --features mock --example concurrent_requests`
+#[apply(main!)]
+async fn main() -> Result<(), Error> {
+    let mock = MockReceiver::start().await?;
+    let receiver = Receiver::new();
+    receiver.connect(mock.addr()).await?;
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let requests = (0..CONCURRENCY).map(|_| receiver.status());
+        for result in future::join_all(requests).await {
+            result?;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let total = CONCURRENCY * ROUNDS;
+    println!(
+        "{} requests ({} concurrent) in {:.2?}, {:.0} requests/s",
+        total,
+        CONCURRENCY,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+
+    receiver.disconnect().await;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+extern crate cast_sender;
+
+use futures_util::StreamExt;
+use macro_rules_attribute::apply;
+use smol_macros::main;
+
+use cast_sender::discovery::{Discovery, DiscoveryEvent};
+use cast_sender::Error;
+
+#[apply(main!)]
+async fn main() -> Result<(), Error> {
+    let discovery = Discovery::new()?;
+    let mut events = Box::pin(discovery.browse()?);
+
+    while let Some(event) = events.next().await {
+        match event {
+            DiscoveryEvent::Added(device) => {
+                println!(
+                    "Found {} ({:?})",
+                    device.friendly_name,
+                    device.socket_addr()
+                )
+            }
+            DiscoveryEvent::Updated(device) => println!("Updated {}", device.friendly_name),
+            DiscoveryEvent::Removed(device) => println!("Lost {}", device.friendly_name),
+        }
+    }
+
+    Ok(())
+}
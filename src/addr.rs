@@ -0,0 +1,315 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::str::FromStr;
+
+use crate::discovery::DiscoveredDevice;
+use crate::namespace::media::DeviceCapabilities;
+use crate::Error;
+
+/// Port cast receivers are listening on, unless announced otherwise (e.g. speaker groups)
+pub const DEFAULT_PORT: u16 = 8009;
+
+/// Address of a cast receiver. The host can either be an IP address, or a
+/// hostname which gets resolved when connecting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastAddr {
+    host: String,
+    port: u16,
+    // Kept as is for IP addresses, a link-local IPv6 address needs its scope id
+    socket_addr: Option<SocketAddr>,
+    // Known from discovery, see `Receiver::capabilities`
+    capabilities: Option<DeviceCapabilities>,
+}
+
+impl CastAddr {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            socket_addr: None,
+            capabilities: None,
+        }
+    }
+
+    /// Remembers what the receiver is capable of, e.g. to reject video on speakers
+    pub fn with_capabilities(mut self, capabilities: DeviceCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn capabilities(&self) -> Option<DeviceCapabilities> {
+        self.capabilities
+    }
+
+    pub(crate) async fn resolve(&self) -> Result<Vec<SocketAddr>, Error> {
+        if let Some(addr) = self.socket_addr {
+            return Ok(vec![addr]);
+        }
+        if let Ok(ip) = self.host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, self.port)]);
+        }
+
+        let addrs = async_net::resolve((self.host.as_str(), self.port)).await?;
+        if addrs.is_empty() {
+            return Err(Error::InvalidAddress(format!(
+                "{} did not resolve to any address",
+                self.host
+            )));
+        }
+
+        Ok(addrs)
+    }
+}
+
+impl FromStr for CastAddr {
+    type Err = Error;
+
+    /// Accepts `host`, `host:port`, `ip`, `ip:port`, `ipv6` and `[ipv6]:port`.
+    /// IPv6 addresses can have a `%scope` suffix, the port defaults to [`DEFAULT_PORT`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAddress(s.to_string());
+
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(addr.into());
+        }
+
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            return Ok(ip.into());
+        }
+
+        // Plain IPv6 address without port, which can't be split at the colon. Parsed as
+        // socket address with the default port, as only that keeps a `%scope` suffix.
+        if let Ok(addr) = format!("[{s}]:{DEFAULT_PORT}").parse::<SocketAddr>() {
+            return Ok(addr.into());
+        }
+
+        // IPv6 address in brackets without port
+        if s.starts_with('[') && s.ends_with(']') {
+            let addr = format!("{s}:{DEFAULT_PORT}").parse::<SocketAddr>();
+            return addr.map(Into::into).map_err(|_| invalid());
+        }
+
+        let (host, port) = match s.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (s, DEFAULT_PORT),
+        };
+
+        // IPv6 addresses were handled above, so brackets and colons are left over garbage
+        let invalid_char = |c: char| c.is_whitespace() || matches!(c, '/' | ':' | '[' | ']');
+        if host.is_empty() || host.contains(invalid_char) {
+            return Err(invalid());
+        }
+
+        Ok(Self::new(host, port))
+    }
+}
+
+impl fmt::Display for CastAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(addr) = self.socket_addr {
+            write!(f, "{addr}")
+        } else if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl From<SocketAddr> for CastAddr {
+    fn from(addr: SocketAddr) -> Self {
+        Self {
+            socket_addr: Some(addr),
+            ..Self::new(addr.ip().to_string(), addr.port())
+        }
+    }
+}
+
+impl From<IpAddr> for CastAddr {
+    fn from(ip: IpAddr) -> Self {
+        SocketAddr::new(ip, DEFAULT_PORT).into()
+    }
+}
+
+/// Conversion into a [`CastAddr`], similar to [`std::net::ToSocketAddrs`].
+pub trait ToCastAddr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error>;
+}
+
+impl ToCastAddr for CastAddr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl ToCastAddr for str {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        self.parse()
+    }
+}
+
+impl ToCastAddr for String {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        self.parse()
+    }
+}
+
+impl ToCastAddr for SocketAddr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok((*self).into())
+    }
+}
+
+impl ToCastAddr for SocketAddrV4 {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(SocketAddr::V4(*self).into())
+    }
+}
+
+impl ToCastAddr for SocketAddrV6 {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(SocketAddr::V6(*self).into())
+    }
+}
+
+impl ToCastAddr for IpAddr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok((*self).into())
+    }
+}
+
+impl ToCastAddr for Ipv4Addr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(IpAddr::V4(*self).into())
+    }
+}
+
+impl ToCastAddr for Ipv6Addr {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(IpAddr::V6(*self).into())
+    }
+}
+
+impl ToCastAddr for (IpAddr, u16) {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(SocketAddr::new(self.0, self.1).into())
+    }
+}
+
+impl ToCastAddr for (&str, u16) {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(CastAddr::new(self.0, self.1))
+    }
+}
+
+impl ToCastAddr for (String, u16) {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        Ok(CastAddr::new(self.0.clone(), self.1))
+    }
+}
+
+impl ToCastAddr for DiscoveredDevice {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        let addr = match self.socket_addr() {
+            Some(addr) => addr.into(),
+            None => CastAddr::new(self.hostname.trim_end_matches('.'), self.port),
+        };
+        Ok(addr.with_capabilities(self.capabilities))
+    }
+}
+
+impl<T: ToCastAddr + ?Sized> ToCastAddr for &T {
+    fn to_cast_addr(&self) -> Result<CastAddr, Error> {
+        (**self).to_cast_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> CastAddr {
+        s.parse().unwrap()
+    }
+
+    fn socket_addr(s: &str) -> Option<SocketAddr> {
+        parse(s).socket_addr
+    }
+
+    #[test]
+    fn parses_ip_addresses() {
+        assert_eq!(
+            socket_addr("192.168.1.2"),
+            Some(([192, 168, 1, 2], 8009).into())
+        );
+        assert_eq!(
+            socket_addr("192.168.1.2:32100"),
+            Some(([192, 168, 1, 2], 32100).into())
+        );
+
+        let localhost = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(socket_addr("::1"), Some((localhost, 8009).into()));
+        assert_eq!(socket_addr("[::1]"), Some((localhost, 8009).into()));
+        assert_eq!(socket_addr("[::1]:32100"), Some((localhost, 32100).into()));
+
+        // Without brackets, the last group is part of the address and not a port
+        let addr = parse("fe80::1:8009");
+        assert_eq!(addr.host(), "fe80::1:8009");
+        assert_eq!(addr.port(), 8009);
+    }
+
+    #[test]
+    fn keeps_ipv6_scope_id() {
+        let link_local = "fe80::1".parse::<Ipv6Addr>().unwrap();
+        let expected = SocketAddr::V6(SocketAddrV6::new(link_local, 8009, 0, 3));
+        assert_eq!(socket_addr("fe80::1%3"), Some(expected));
+        assert_eq!(socket_addr("[fe80::1%3]"), Some(expected));
+        assert_eq!(socket_addr("[fe80::1%3]:8009"), Some(expected));
+
+        let addr = CastAddr::from(expected);
+        assert_eq!(addr.to_string(), "[fe80::1%3]:8009");
+        let resolved = smol::block_on(addr.resolve()).unwrap();
+        assert_eq!(resolved, [expected]);
+    }
+
+    #[test]
+    fn parses_hostnames() {
+        let addr = parse("chromecast.local");
+        assert_eq!((addr.host(), addr.port()), ("chromecast.local", 8009));
+        assert_eq!(addr.socket_addr, None);
+
+        let addr = parse("chromecast.local:32100");
+        assert_eq!((addr.host(), addr.port()), ("chromecast.local", 32100));
+        assert_eq!(addr.to_string(), "chromecast.local:32100");
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for s in [
+            "",
+            ":8009",
+            "chromecast.local:",
+            "chromecast.local:port",
+            "chromecast.local:65536",
+            "[::1]:port",
+            "[::1",
+            "chromecast:local:8009",
+            "[chromecast.local]",
+            "chrome cast",
+            "http://chromecast.local",
+        ] {
+            assert!(
+                matches!(s.parse::<CastAddr>(), Err(Error::InvalidAddress(_))),
+                "{s:?} got accepted"
+            );
+        }
+    }
+}
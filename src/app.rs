@@ -30,13 +30,14 @@ impl App {
                 NamespaceUrn::Heartbeat,
                 NamespaceUrn::Receiver,
                 NamespaceUrn::DeviceAuth,
+                NamespaceUrn::Multizone,
             ],
             ..Default::default()
         }
     }
 }
 
-#[derive(EnumString, Display, Debug, Clone, Default, PartialEq, Eq)]
+#[derive(EnumString, Display, Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum AppId {
     #[default]
     #[strum(serialize = "CC1AD845")]
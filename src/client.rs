@@ -1,15 +1,17 @@
-use std::net::SocketAddr;
+use std::net::Shutdown;
 use std::sync::Arc;
 
+use async_channel::Sender;
 use async_native_tls::{TlsConnector, TlsStream};
 use async_net::TcpStream;
 use futures_util::io::{ReadHalf, WriteHalf};
-use futures_util::{AsyncReadExt, AsyncWriteExt};
+use futures_util::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use prost::Message;
-use smol::lock::Mutex;
+use serde_json::Value;
 
+use super::namespace::{Custom, CustomBinary, NamespaceUrn};
 use super::proto;
-use super::{namespace::NamespaceUrn, Error, Payload};
+use super::{Error, Payload, ToCastAddr};
 
 #[derive(Debug, Clone)]
 pub struct Response {
@@ -24,64 +26,121 @@ pub struct Response {
     pub request_id: Option<u32>,
 }
 
+/// Sender id used by [`Client::send`] and the primary [`crate::Receiver`]
+pub const DEFAULT_SENDER_ID: &str = "sender-0";
+
+// Number of messages which can be queued for sending before `send` waits
+const WRITE_QUEUE_CAPACITY: usize = 64;
+// Number of received messages which get buffered until they are picked up
+const READ_QUEUE_CAPACITY: usize = 64;
+
+/// Handle to a connection with a receiver. Reading and writing happen on
+/// dedicated tasks, all clones of a client share the same connection.
 #[derive(Debug, Clone)]
 pub struct Client {
-    read_stream: Arc<Mutex<ReadHalf<TlsStream<TcpStream>>>>,
-    write_stream: Arc<Mutex<WriteHalf<TlsStream<TcpStream>>>>,
+    tcp_stream: TcpStream,
+    // Encoded frames which get written by the writer task
+    outgoing: Sender<Vec<u8>>,
+    incoming: async_channel::Receiver<Result<RawMessage, Error>>,
+    // DER encoded, signed by the receiver during device authentication
+    peer_certificate: Option<Arc<[u8]>>,
 }
 
 impl Client {
-    pub async fn connect(addr: &str) -> Result<Self, Error> {
-        let addr = SocketAddr::new(addr.parse()?, 8009);
+    pub async fn connect<A: ToCastAddr>(addr: A) -> Result<Self, Error> {
+        let addr = addr.to_cast_addr()?;
+        let socket_addrs = addr.resolve().await?;
 
-        // Casts devices are using self signed certs
+        // Casts devices are using self signed certs, use `Receiver::verify_device` to authenticate them
         let tls_connector = TlsConnector::new().danger_accept_invalid_certs(true);
-        let tcp_stream = TcpStream::connect(&addr).await?;
+        let tcp_stream = TcpStream::connect(&socket_addrs[..]).await?;
 
         let tls_stream = tls_connector
-            .connect(addr.to_string(), tcp_stream.clone())
+            .connect(addr.host(), tcp_stream.clone())
             .await?;
 
+        let peer_certificate = tls_stream
+            .peer_certificate()?
+            .map(|cert| cert.to_der())
+            .transpose()?;
         let (read_stream, write_stream) = tls_stream.split();
 
+        let (outgoing, outgoing_rx) = async_channel::bounded(WRITE_QUEUE_CAPACITY);
+        let (incoming_tx, incoming) = async_channel::bounded(READ_QUEUE_CAPACITY);
+        smol::spawn(Self::write_task(
+            write_stream,
+            outgoing_rx,
+            tcp_stream.clone(),
+        ))
+        .detach();
+        smol::spawn(Self::read_task(read_stream, incoming_tx)).detach();
+
         Ok(Self {
-            read_stream: Arc::new(Mutex::new(read_stream)),
-            write_stream: Arc::new(Mutex::new(write_stream)),
+            tcp_stream,
+            outgoing,
+            incoming,
+            peer_certificate: peer_certificate.map(Into::into),
         })
     }
 
-    pub async fn receive(&self) -> Result<Response, Error> {
-        let mut read_stream = self.read_stream.lock().await;
+    async fn write_task(
+        mut stream: WriteHalf<TlsStream<TcpStream>>,
+        outgoing: async_channel::Receiver<Vec<u8>>,
+        tcp_stream: TcpStream,
+    ) {
+        while let Ok(frames) = outgoing.recv().await {
+            if let Err(err) = stream.write_all(&frames).await {
+                debug!("Unable to write message: {}", err);
+                break;
+            }
+        }
 
-        // The first package is a u32 specifying the packet length....
-        let mut buf: [u8; 4] = [0; 4];
-        read_stream.read_exact(&mut buf).await?;
-        let len = u32::from_be_bytes(buf);
+        // Either all clients got dropped, or the connection is broken. The reader
+        // notices the shutdown and reports it to whoever is still receiving.
+        outgoing.close();
+        let _ = tcp_stream.shutdown(Shutdown::Both);
+    }
 
-        // ... then get the actual package with the specified length
-        let mut buf: Vec<u8> = vec![0; len as usize];
-        read_stream.read_exact(&mut buf).await?;
+    async fn read_task(
+        mut stream: ReadHalf<TlsStream<TcpStream>>,
+        incoming: Sender<Result<RawMessage, Error>>,
+    ) {
+        loop {
+            let result = read_message(&mut stream).await;
+            let failed = result.is_err();
 
-        let msg: proto::CastMessage = proto::CastMessage::decode(&buf[..])?;
-        let ns: NamespaceUrn = msg.namespace.parse().unwrap();
-        let mut pl: PayloadData = serde_json::from_str(msg.payload_utf8())?;
+            // Stop once nobody is receiving anymore, or the connection is unusable
+            if incoming.send(result).await.is_err() || failed {
+                break;
+            }
+        }
+    }
 
-        if let Payload::Custom(u) = &mut pl.data {
-            u.namespace = ns.clone();
-        };
+    /// The DER encoded TLS certificate the receiver presented
+    pub fn peer_certificate(&self) -> Option<&[u8]> {
+        self.peer_certificate.as_deref()
+    }
 
-        debug!(
-            "[RECV] {} -> {} | Namespace: {:?} | Request: {:?}",
-            msg.source_id, msg.destination_id, ns, pl.request_id
-        );
-        debug!("       {:#?}", pl);
-        Ok(Response {
-            source_id: msg.source_id,
-            destination_id: msg.destination_id,
-            namespace: ns,
-            payload: pl.data,
-            request_id: pl.request_id,
-        })
+    /// Shuts down the connection, pending and future reads and writes will fail.
+    pub fn close(&self) {
+        self.outgoing.close();
+        let _ = self.tcp_stream.shutdown(Shutdown::Both);
+    }
+
+    /// Whether both clients share the same underlying connection
+    pub fn is_same(&self, other: &Client) -> bool {
+        self.outgoing.same_channel(&other.outgoing)
+    }
+
+    /// Receives the next message, with its payload deserialized according to its namespace
+    pub async fn receive(&self) -> Result<Response, Error> {
+        Ok(decode_message(self.receive_raw().await?))
+    }
+
+    /// Receives the next message without deserializing its payload. Each message
+    /// is only received once, even if multiple clones of the client are receiving.
+    pub async fn receive_raw(&self) -> Result<RawMessage, Error> {
+        self.incoming.recv().await?
     }
 
     pub async fn send<P: Into<Payload>>(
@@ -90,48 +149,411 @@ impl Client {
         payload: P,
         request_id: Option<u32>,
     ) -> Result<(), Error> {
-        let payload: Payload = payload.into();
-        let payload_data = PayloadData {
+        self.send_from(
+            DEFAULT_SENDER_ID.into(),
+            destination_id,
+            payload,
             request_id,
-            data: payload.clone(),
+        )
+        .await
+    }
+
+    /// Same as [`Client::send`], but from the virtual connection of `source_id`
+    pub async fn send_from<P: Into<Payload>>(
+        &self,
+        source_id: String,
+        destination_id: String,
+        payload: P,
+        request_id: Option<u32>,
+    ) -> Result<(), Error> {
+        let payload: Payload = payload.into();
+        let msg = encode_message(source_id, destination_id, &payload, request_id)?;
+        self.send_raw(&msg).await
+    }
+
+    /// Sends a message whose payload is already serialized. Waits while the
+    /// send queue is full, but returns before the message is actually written.
+    pub async fn send_raw(&self, msg: &RawMessage) -> Result<(), Error> {
+        let frames = encode_frames(msg)?;
+        self.outgoing
+            .send(frames)
+            .await
+            .map_err(|_| Error::NoConnection)
+    }
+}
+
+/// A message as it gets transferred, before its payload got deserialized
+#[derive(Debug, Clone)]
+pub struct RawMessage {
+    pub source_id: String,
+    pub destination_id: String,
+    pub namespace: NamespaceUrn,
+    pub payload: RawPayload,
+}
+
+#[derive(Debug, Clone)]
+pub enum RawPayload {
+    /// JSON, used by most namespaces
+    String(String),
+    /// Protobuf or app specific binary data
+    Binary(Vec<u8>),
+}
+
+/// Maximum size of a single CASTV2 frame, excluding its length prefix
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// Maximum size of a reassembled message payload
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+// Room for the payload field header, `continued` and `remaining_length` of a chunk
+const CHUNK_OVERHEAD: usize = 16;
+
+impl RawPayload {
+    fn len(&self) -> usize {
+        match self {
+            RawPayload::String(string) => string.len(),
+            RawPayload::Binary(binary) => binary.len(),
+        }
+    }
+
+    /// Returns the part of the payload starting at `start` with at most `max_len` bytes
+    fn chunk(&self, start: usize, max_len: usize) -> RawPayload {
+        let end = (start + max_len).min(self.len());
+        match self {
+            RawPayload::String(string) => {
+                // Chunks have to be valid UTF-8 on their own
+                let mut end = end;
+                while !string.is_char_boundary(end) {
+                    end -= 1;
+                }
+                RawPayload::String(string[start..end].to_string())
+            }
+            RawPayload::Binary(binary) => RawPayload::Binary(binary[start..end].to_vec()),
+        }
+    }
+
+    fn append(&mut self, chunk: RawPayload) -> Result<(), Error> {
+        match (self, chunk) {
+            (RawPayload::String(string), RawPayload::String(chunk)) => string.push_str(&chunk),
+            (RawPayload::Binary(binary), RawPayload::Binary(chunk)) => binary.extend(chunk),
+            _ => {
+                return Err(Error::Protocol(
+                    "Payload type changed between chunks".into(),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RawMessage {
+    fn from_cast_message(msg: proto::CastMessage) -> Result<Self, Error> {
+        let payload = match (msg.payload_type(), msg.payload_utf8, msg.payload_binary) {
+            (proto::cast_message::PayloadType::String, Some(string), _) => {
+                RawPayload::String(string)
+            }
+            (proto::cast_message::PayloadType::Binary, _, Some(binary)) => {
+                RawPayload::Binary(binary)
+            }
+            (payload_type, _, _) => {
+                return Err(Error::Protocol(format!(
+                    "Missing {} payload",
+                    payload_type.as_str_name()
+                )))
+            }
         };
 
-        let payload_json = serde_json::to_string(&payload_data).unwrap();
-        let msg = proto::CastMessage {
+        let namespace = msg
+            .namespace
+            .parse()
+            .map_err(|_| Error::Protocol(format!("Invalid namespace {}", msg.namespace)))?;
+
+        Ok(Self {
+            source_id: msg.source_id,
+            destination_id: msg.destination_id,
+            namespace,
+            payload,
+        })
+    }
+
+    fn to_cast_message(&self, payload: RawPayload) -> proto::CastMessage {
+        let (payload_type, payload_utf8, payload_binary) = match payload {
+            RawPayload::String(string) => {
+                (proto::cast_message::PayloadType::String, Some(string), None)
+            }
+            RawPayload::Binary(binary) => {
+                (proto::cast_message::PayloadType::Binary, None, Some(binary))
+            }
+        };
+
+        proto::CastMessage {
             protocol_version: proto::cast_message::ProtocolVersion::Castv210.into(),
-            source_id: "sender-0".into(),
-            destination_id,
-            namespace: payload.namespace().to_string(),
-            payload_type: proto::cast_message::PayloadType::String.into(),
-            payload_utf8: Some(payload_json.clone()),
-            payload_binary: None,
+            source_id: self.source_id.clone(),
+            destination_id: self.destination_id.clone(),
+            namespace: self.namespace.to_string(),
+            payload_type: payload_type.into(),
+            payload_utf8,
+            payload_binary,
             continued: None,
             remaining_length: None,
-        };
+        }
+    }
 
-        debug!(
-            "[SEND] {} -> {} | Namespace: {:?} | Request: {:?}",
-            msg.source_id,
-            msg.destination_id,
-            payload.namespace(),
-            request_id,
-        );
-        debug!("       {}", payload_json);
+    /// Splits the message into frames which don't exceed [`MAX_FRAME_SIZE`]
+    fn to_frames(&self) -> Result<Vec<proto::CastMessage>, Error> {
+        let total = self.payload.len();
+        if total > MAX_MESSAGE_SIZE {
+            return Err(Error::MessageTooLarge(total));
+        }
 
-        let mut write_stream = self.write_stream.lock().await;
-        let len: u32 = msg.encoded_len().try_into().unwrap();
+        // Messages which fit into a single frame are sent without chunk fields,
+        // since not all receivers know about them
+        let msg = self.to_cast_message(self.payload.clone());
+        if msg.encoded_len() <= MAX_FRAME_SIZE {
+            return Ok(vec![msg]);
+        }
 
-        // First send package length
-        write_stream.write_all(&len.to_be_bytes()).await?;
+        let header_len = self.to_cast_message(self.payload.chunk(0, 0)).encoded_len();
+        let chunk_len = MAX_FRAME_SIZE
+            .checked_sub(header_len + CHUNK_OVERHEAD)
+            .filter(|len| *len > 0)
+            .ok_or(Error::FrameTooLarge(header_len))?;
 
-        // Then the actual package
-        write_stream.write_all(&msg.encode_to_vec()).await?;
+        let mut frames = Vec::new();
+        let mut start = 0;
+        while start < total {
+            let chunk = self.payload.chunk(start, chunk_len);
+            if chunk.len() == 0 {
+                return Err(Error::FrameTooLarge(header_len + chunk_len));
+            }
+            start += chunk.len();
 
-        Ok(())
+            let mut frame = self.to_cast_message(chunk);
+            frame.continued = Some(start < total);
+            frame.remaining_length = Some((total - start) as u32);
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn is_same_message(&self, frame: &proto::CastMessage) -> bool {
+        self.source_id == frame.source_id
+            && self.destination_id == frame.destination_id
+            && self.namespace.to_string() == frame.namespace
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Reads a single length-prefixed frame from the stream
+async fn read_frame<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Vec<u8>, Error> {
+    // The first package is a u32 specifying the packet length....
+    let mut buf: [u8; 4] = [0; 4];
+    stream.read_exact(&mut buf).await?;
+    let len = u32::from_be_bytes(buf) as usize;
+
+    // Don't let the peer decide how much memory gets allocated
+    if len > MAX_FRAME_SIZE {
+        return Err(Error::FrameTooLarge(len));
+    }
+
+    // ... then get the actual package with the specified length
+    let mut buf: Vec<u8> = vec![0; len];
+    stream.read_exact(&mut buf).await?;
+
+    Ok(buf)
+}
+
+/// Reads the next message from the stream, reassembling it if it was split into chunks.
+/// Only errors which break the framing get returned, frames which can't be decoded
+/// are skipped.
+pub(crate) async fn read_message<R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> Result<RawMessage, Error> {
+    let (mut msg, mut continued) = loop {
+        let frame = read_frame(stream).await?;
+        let result = proto::CastMessage::decode(&frame[..])
+            .map_err(Error::from)
+            .and_then(|frame| {
+                let continued = frame.continued();
+                Ok((RawMessage::from_cast_message(frame)?, continued))
+            });
+
+        match result {
+            Ok(result) => break result,
+            Err(err) => warn!("Skipping frame which can't be decoded: {}", err),
+        }
+    };
+
+    while continued {
+        // Without the chunk, it's unknown where the message ends
+        let frame = proto::CastMessage::decode(&read_frame(stream).await?[..])?;
+        if !msg.is_same_message(&frame) {
+            return Err(Error::Protocol(format!(
+                "Received frame of another message while reassembling {} message",
+                msg.namespace
+            )));
+        }
+
+        let remaining = frame.remaining_length.unwrap_or_default() as usize;
+        continued = frame.continued();
+        msg.payload
+            .append(RawMessage::from_cast_message(frame)?.payload)?;
+
+        let len = msg.payload.len();
+        if len.saturating_add(remaining) > MAX_MESSAGE_SIZE {
+            return Err(Error::MessageTooLarge(len.saturating_add(remaining)));
+        }
+    }
+
+    Ok(msg)
+}
+
+/// Encodes the message as length-prefixed frames, split into chunks if it exceeds [`MAX_FRAME_SIZE`]
+pub(crate) fn encode_frames(msg: &RawMessage) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    for frame in msg.to_frames()? {
+        let len = frame.encoded_len();
+        if len > MAX_FRAME_SIZE {
+            return Err(Error::FrameTooLarge(len));
+        }
+
+        // First the package length, then the actual package
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+        buf.extend(frame.encode_to_vec());
+    }
+
+    Ok(buf)
+}
+
+/// Deserializes the payload according to the namespace. Payloads which don't match
+/// the types of their namespace become [`Payload::Unknown`], or [`Payload::CustomBinary`].
+pub(crate) fn decode_message(msg: RawMessage) -> Response {
+    let ns = msg.namespace;
+
+    let (payload, request_id) = match msg.payload {
+        RawPayload::Binary(data) => {
+            // Binary namespaces are protobuf encoded
+            let payload = match &ns {
+                NamespaceUrn::DeviceAuth => proto::DeviceAuthMessage::decode(&data[..])
+                    .map(Payload::DeviceAuth)
+                    .map_err(|err| warn!("Unable to decode {} payload: {}", ns, err))
+                    .ok(),
+                _ => None,
+            };
+
+            let payload = payload.unwrap_or_else(|| {
+                Payload::CustomBinary(CustomBinary {
+                    namespace: ns.clone(),
+                    data,
+                })
+            });
+            (payload, None)
+        }
+        RawPayload::String(json) => {
+            let mut value = serde_json::from_str::<Value>(&json).ok();
+            let request_id = value
+                .as_mut()
+                .and_then(|value| value.as_object_mut())
+                .and_then(|object| object.remove("requestId"))
+                .and_then(|request_id| request_id.as_u64())
+                .map(|request_id| request_id as u32);
+
+            let payload = match value.map(|value| decode_json(&ns, value)) {
+                Some(Ok(payload)) => payload,
+                result => {
+                    if let Some(Err(err)) = result {
+                        warn!("Unable to deserialize {} payload: {}", ns, err);
+                    }
+                    Payload::Unknown {
+                        namespace: ns.clone(),
+                        raw_json: json,
+                    }
+                }
+            };
+            (payload, request_id)
+        }
+    };
+
+    debug!(
+        "[RECV] {} -> {} | Namespace: {:?} | Request: {:?}",
+        msg.source_id, msg.destination_id, ns, request_id
+    );
+    debug!("       {:#?}", payload);
+    Response {
+        source_id: msg.source_id,
+        destination_id: msg.destination_id,
+        namespace: ns,
+        payload,
+        request_id,
+    }
+}
+
+fn decode_json(ns: &NamespaceUrn, mut value: Value) -> Result<Payload, serde_json::Error> {
+    // The receiver answers `GET_APP_AVAILABILITY` with its type in `responseType`.
+    // Other namespaces, custom ones in particular, keep their fields untouched.
+    if *ns == NamespaceUrn::Receiver {
+        if let Some(object) = value.as_object_mut() {
+            if !object.contains_key("type") {
+                if let Some(response_type) = object.remove("responseType") {
+                    object.insert("type".into(), response_type);
+                }
+            }
+        }
+    }
+
+    // Message types aren't unique across namespaces, e.g. `PONG`
+    Ok(match ns {
+        NamespaceUrn::Connection => Payload::Connection(serde_json::from_value(value)?),
+        NamespaceUrn::Heartbeat => Payload::Heartbeat(serde_json::from_value(value)?),
+        NamespaceUrn::Media => Payload::Media(serde_json::from_value(value)?),
+        NamespaceUrn::Multizone => Payload::Multizone(serde_json::from_value(value)?),
+        NamespaceUrn::Receiver => Payload::Receiver(serde_json::from_value(value)?),
+        _ => Payload::Custom(Custom {
+            namespace: ns.clone(),
+            fields: serde_json::from_value(value)?,
+        }),
+    })
+}
+
+pub(crate) fn encode_message(
+    source_id: String,
+    destination_id: String,
+    payload: &Payload,
+    request_id: Option<u32>,
+) -> Result<RawMessage, Error> {
+    let raw_payload = match payload {
+        Payload::DeviceAuth(message) => RawPayload::Binary(message.encode_to_vec()),
+        Payload::CustomBinary(custom) => RawPayload::Binary(custom.data.clone()),
+        Payload::Unknown { raw_json, .. } => RawPayload::String(raw_json.clone()),
+        _ => {
+            let payload_data = PayloadData {
+                request_id,
+                data: payload.clone(),
+            };
+            let json = serde_json::to_string(&payload_data)
+                .map_err(|err| Error::Protocol(format!("Unable to serialize payload: {}", err)))?;
+            RawPayload::String(json)
+        }
+    };
+
+    debug!(
+        "[SEND] {} -> {} | Namespace: {:?} | Request: {:?}",
+        source_id,
+        destination_id,
+        payload.namespace(),
+        request_id,
+    );
+    debug!("       {:?}", raw_payload);
+
+    Ok(RawMessage {
+        source_id,
+        destination_id,
+        namespace: payload.namespace(),
+        payload: raw_payload,
+    })
+}
+
+#[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct PayloadData {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -139,3 +561,161 @@ struct PayloadData {
     #[serde(flatten)]
     data: Payload,
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::io::Cursor;
+
+    use super::*;
+
+    fn message(payload: RawPayload) -> RawMessage {
+        RawMessage {
+            source_id: DEFAULT_SENDER_ID.into(),
+            destination_id: "receiver-0".into(),
+            namespace: NamespaceUrn::Media,
+            payload,
+        }
+    }
+
+    fn frame_count(mut buf: &[u8]) -> usize {
+        let mut count = 0;
+        while !buf.is_empty() {
+            let len = u32::from_be_bytes(buf[..4].try_into().unwrap()) as usize;
+            assert!(len <= MAX_FRAME_SIZE);
+            buf = &buf[4 + len..];
+            count += 1;
+        }
+        count
+    }
+
+    fn encode(frames: Vec<proto::CastMessage>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for frame in frames {
+            buf.extend_from_slice(&(frame.encoded_len() as u32).to_be_bytes());
+            buf.extend(frame.encode_to_vec());
+        }
+        buf
+    }
+
+    fn read(buf: Vec<u8>) -> Result<RawMessage, Error> {
+        smol::block_on(read_message(&mut Cursor::new(buf)))
+    }
+
+    // Returns the number of frames the message got split into
+    fn assert_round_trip(payload: RawPayload) -> usize {
+        let msg = message(payload);
+        let buf = encode_frames(&msg).unwrap();
+        let frames = frame_count(&buf);
+        let read = read(buf).unwrap();
+
+        assert_eq!(read.source_id, msg.source_id);
+        assert_eq!(read.destination_id, msg.destination_id);
+        assert_eq!(read.namespace, msg.namespace);
+        match (read.payload, msg.payload) {
+            (RawPayload::String(read), RawPayload::String(sent)) => assert_eq!(read, sent),
+            (RawPayload::Binary(read), RawPayload::Binary(sent)) => assert_eq!(read, sent),
+            (read, sent) => panic!("sent {sent:?}, read {read:?}"),
+        }
+        frames
+    }
+
+    #[test]
+    fn round_trips_around_max_frame_size() {
+        // Largest payload which still fits into a single frame
+        let fits = (MAX_FRAME_SIZE - 100..MAX_FRAME_SIZE)
+            .rev()
+            .find(|len| {
+                let msg = message(RawPayload::String("a".repeat(*len)));
+                msg.to_frames().unwrap().len() == 1
+            })
+            .unwrap();
+
+        assert_eq!(assert_round_trip(RawPayload::String("a".repeat(fits))), 1);
+        assert_eq!(
+            assert_round_trip(RawPayload::String("a".repeat(fits + 1))),
+            2
+        );
+    }
+
+    #[test]
+    fn keeps_multi_byte_characters_intact() {
+        // Shift the characters, so that each of their bytes ends up at a chunk boundary once
+        for prefix in ["", "a", "aa"] {
+            let payload = format!("{prefix}{}", "€".repeat(MAX_FRAME_SIZE));
+            assert!(assert_round_trip(RawPayload::String(payload)) > 1);
+        }
+    }
+
+    #[test]
+    fn round_trips_binary_payloads() {
+        let payload: Vec<u8> = (0..3 * MAX_FRAME_SIZE).map(|i| i as u8).collect();
+        assert_eq!(assert_round_trip(RawPayload::Binary(payload)), 4);
+        assert_eq!(assert_round_trip(RawPayload::Binary(vec![0, 255])), 1);
+    }
+
+    #[test]
+    fn rejects_interleaved_frames() {
+        let chunked = message(RawPayload::String("a".repeat(2 * MAX_FRAME_SIZE)));
+        let mut frames = chunked.to_frames().unwrap();
+        let foreign = RawMessage {
+            source_id: "sender-1".into(),
+            ..message(RawPayload::String("{}".into()))
+        };
+        frames.insert(1, foreign.to_frames().unwrap().remove(0));
+
+        assert!(matches!(read(encode(frames)), Err(Error::Protocol(_))));
+    }
+
+    #[test]
+    fn skips_frames_which_cant_be_decoded() {
+        let mut missing_payload = message(RawPayload::String("{}".into()))
+            .to_frames()
+            .unwrap();
+        missing_payload[0].payload_utf8 = None;
+        let mut buf = encode(missing_payload);
+
+        // Not a protobuf message at all
+        buf.extend_from_slice(&3u32.to_be_bytes());
+        buf.extend_from_slice(&[0xff; 3]);
+
+        let valid = message(RawPayload::String(r#"{"type":"GET_STATUS"}"#.into()));
+        buf.extend(encode_frames(&valid).unwrap());
+
+        let read = read(buf).unwrap();
+        assert!(matches!(read.payload, RawPayload::String(json) if json.contains("GET_STATUS")));
+    }
+
+    #[test]
+    fn renames_response_type_only_for_receiver_namespace() {
+        let json = r#"{"requestId":1,"responseType":"GET_APP_AVAILABILITY","availability":{}}"#;
+        let response = decode_message(RawMessage {
+            namespace: NamespaceUrn::Receiver,
+            ..message(RawPayload::String(json.into()))
+        });
+        assert!(matches!(
+            response.payload,
+            Payload::Receiver(crate::namespace::receiver::Receiver::GetAppAvailability(_))
+        ));
+
+        let namespace: NamespaceUrn = "urn:x-cast:com.example.test".parse().unwrap();
+        assert!(matches!(namespace, NamespaceUrn::Custom(_)));
+        let json = r#"{"responseType":"PONG"}"#;
+        let response = decode_message(RawMessage {
+            namespace,
+            ..message(RawPayload::String(json.into()))
+        });
+        let Payload::Custom(custom) = response.payload else {
+            panic!("expected a custom payload, got {:?}", response.payload);
+        };
+        assert_eq!(custom.fields.get("responseType"), Some(&"PONG".into()));
+        assert!(!custom.fields.contains_key("type"));
+    }
+
+    #[test]
+    fn rejects_oversized_length_prefix() {
+        let len = MAX_FRAME_SIZE + 1;
+        let mut buf = (len as u32).to_be_bytes().to_vec();
+        buf.resize(4 + len, 0);
+        assert!(matches!(read(buf), Err(Error::FrameTooLarge(l)) if l == len));
+    }
+}
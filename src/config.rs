@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+#[derive(Clone, Debug, Builder)]
+#[builder(setter(strip_option, into), default)]
+pub struct ReceiverConfig {
+    /// Default duration to wait for the response of a request
+    pub request_timeout: Duration,
+    /// Reconnect automatically when the connection gets lost. Disabled by default.
+    pub reconnect_policy: Option<ReconnectPolicy>,
+    /// Detect dead connections by sending pings. Enabled by default.
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// DER encoded root certificates or public keys which receivers have to prove a certificate
+    /// chain to, see [`crate::Receiver::verify_device`]. Defaults to the bundled
+    /// [`crate::cast_roots`], keys of an `AuthorityKeys` blob can be loaded with
+    /// [`crate::authority_keys`].
+    pub device_auth_roots: Vec<Vec<u8>>,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(10),
+            reconnect_policy: None,
+            heartbeat: Some(HeartbeatConfig::default()),
+            device_auth_roots: crate::cast_roots(),
+        }
+    }
+}
+
+/// Exponential backoff used for reconnecting, the delay doubles with every failed attempt.
+#[derive(Clone, Debug, Builder)]
+#[builder(setter(strip_option, into), default)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Give up after this many failed attempts, `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Builder)]
+#[builder(setter(strip_option, into), default)]
+pub struct HeartbeatConfig {
+    /// How often the receiver gets pinged
+    pub interval: Duration,
+    /// The connection is considered dead if no message at all arrived within this duration
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
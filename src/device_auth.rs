@@ -0,0 +1,476 @@
+use std::iter;
+
+use prost::Message;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use x509_parser::prelude::*;
+
+use crate::error::AuthError;
+use crate::namespace::device_auth::*;
+use crate::{proto, Error};
+
+/// A receiver which proved to own a certificate issued by a trusted authority
+#[derive(Clone, Debug)]
+pub struct DeviceIdentity {
+    /// Common name of the device certificate
+    pub common_name: String,
+    /// DER encoded device certificate
+    pub certificate: Vec<u8>,
+    /// DER encoded intermediate certificates, ordered from the device certificate to the root
+    pub intermediate_certificates: Vec<Vec<u8>>,
+}
+
+// Root certificates of Google's Cast authorities, see `certs/README.md`
+const CAST_ROOTS: [&[u8]; 2] = [
+    include_bytes!("../certs/cast_root_ca.der"),
+    include_bytes!("../certs/eureka_root_ca.der"),
+];
+
+/// The bundled DER encoded root certificates of Google's Cast authorities, which are the
+/// default [`crate::ReceiverConfig::device_auth_roots`]
+pub fn cast_roots() -> Vec<Vec<u8>> {
+    CAST_ROOTS
+        .iter()
+        .filter(|root| !root.is_empty())
+        .map(|root| root.to_vec())
+        .collect()
+}
+
+/// Decodes the public keys of a serialized `AuthorityKeys` message, the format Chromium ships
+/// the Cast authority keys in, to be used as [`crate::ReceiverConfig::device_auth_roots`]
+pub fn authority_keys(blob: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let authority_keys = proto::AuthorityKeys::decode(blob)?;
+    Ok(authority_keys
+        .keys
+        .into_iter()
+        .map(|key| key.public_key)
+        .collect())
+}
+
+/// Creates a challenge with a random nonce, which gets returned as well
+pub(crate) fn challenge() -> Result<(DeviceAuthMessage, Vec<u8>), AuthError> {
+    let mut nonce = vec![0; 16];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| Error::Io(std::io::Error::other("Unable to generate nonce")))?;
+
+    let message = DeviceAuthMessage {
+        challenge: Some(AuthChallenge {
+            signature_algorithm: Some(SignatureAlgorithm::RsassaPkcs1v15.into()),
+            sender_nonce: Some(nonce.clone()),
+            hash_algorithm: Some(HashAlgorithm::Sha256.into()),
+        }),
+        response: None,
+        error: None,
+    };
+
+    Ok((message, nonce))
+}
+
+/// Checks that the certificate chain of the response leads to one of the `roots`, and
+/// that the device certificate signed the TLS certificate of the established connection.
+pub(crate) fn verify(
+    message: DeviceAuthMessage,
+    nonce: &[u8],
+    peer_certificate: &[u8],
+    roots: &[Vec<u8>],
+) -> Result<DeviceIdentity, AuthError> {
+    if let Some(error) = message.error {
+        let reason = ErrorType::try_from(error.error_type)
+            .map(|error_type| error_type.as_str_name().to_string())
+            .unwrap_or_else(|_| error.error_type.to_string());
+        return Err(AuthError::Rejected(reason));
+    }
+    let response = message.response.ok_or(AuthError::InvalidResponse)?;
+
+    let device_certificate = parse_certificate(&response.client_auth_certificate)?;
+    let intermediate_certificates = response
+        .intermediate_certificate
+        .iter()
+        .map(|der| parse_certificate(der))
+        .collect::<Result<Vec<_>, _>>()?;
+    let roots = roots
+        .iter()
+        .map(|der| parse_root(der))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every certificate has to be signed by the next one, and the last one by a root
+    let chain: Vec<_> = iter::once(&device_certificate)
+        .chain(&intermediate_certificates)
+        .collect();
+    for certificate in &chain {
+        if !certificate.validity().is_valid() {
+            return Err(AuthError::InvalidCertificate(format!(
+                "{} is expired or not yet valid",
+                certificate.subject()
+            )));
+        }
+    }
+    // Only the device certificate may be used for signing data, and only CAs for issuing
+    // certificates. Otherwise any device of the trusted tree could mint new identities.
+    if !has_key_usage(&device_certificate, KeyUsage::digital_signature)? {
+        return Err(AuthError::InvalidCertificate(format!(
+            "{} is not allowed to sign data",
+            device_certificate.subject()
+        )));
+    }
+    for (below, certificate) in intermediate_certificates.iter().enumerate() {
+        check_issuer(certificate, below)?;
+    }
+
+    for pair in chain.windows(2) {
+        pair[0]
+            .verify_signature(Some(pair[1].public_key()))
+            .map_err(|_| AuthError::UntrustedChain)?;
+    }
+
+    let last = chain[chain.len() - 1];
+    let trusted = roots
+        .iter()
+        .any(|root| last.public_key().raw == root.raw || last.verify_signature(Some(root)).is_ok());
+    if !trusted {
+        return Err(AuthError::UntrustedChain);
+    }
+
+    // Receivers which don't support nonces only sign the TLS certificate
+    let mut signed_data = Vec::new();
+    if let Some(sender_nonce) = &response.sender_nonce {
+        if sender_nonce != nonce {
+            return Err(AuthError::NonceMismatch);
+        }
+        signed_data.extend_from_slice(sender_nonce);
+    }
+    signed_data.extend_from_slice(peer_certificate);
+
+    let algorithm: &dyn VerificationAlgorithm =
+        match (response.signature_algorithm(), response.hash_algorithm()) {
+            (SignatureAlgorithm::RsassaPss, HashAlgorithm::Sha256) => {
+                &signature::RSA_PSS_2048_8192_SHA256
+            }
+            (SignatureAlgorithm::RsassaPss, HashAlgorithm::Sha1) => {
+                return Err(AuthError::InvalidSignature)
+            }
+            (_, HashAlgorithm::Sha256) => &signature::RSA_PKCS1_2048_8192_SHA256,
+            (_, HashAlgorithm::Sha1) => &signature::RSA_PKCS1_2048_8192_SHA1_FOR_LEGACY_USE_ONLY,
+        };
+
+    let public_key = &device_certificate.public_key().subject_public_key.data;
+    UnparsedPublicKey::new(algorithm, public_key)
+        .verify(&signed_data, &response.signature)
+        .map_err(|_| AuthError::InvalidSignature)?;
+
+    let common_name = device_certificate
+        .subject()
+        .iter_common_name()
+        .next()
+        .and_then(|common_name| common_name.as_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    Ok(DeviceIdentity {
+        common_name,
+        certificate: response.client_auth_certificate.clone(),
+        intermediate_certificates: response.intermediate_certificate.clone(),
+    })
+}
+
+// Roots are either certificates, or just their public keys
+fn parse_root(der: &[u8]) -> Result<SubjectPublicKeyInfo<'_>, AuthError> {
+    if let Ok((_, certificate)) = X509Certificate::from_der(der) {
+        return Ok(certificate.tbs_certificate.subject_pki);
+    }
+
+    SubjectPublicKeyInfo::from_der(der)
+        .map(|(_, public_key)| public_key)
+        .map_err(|err| AuthError::InvalidCertificate(err.to_string()))
+}
+
+// Like Chromium's cast certificate verifier, issuers need to be CAs with the keyCertSign usage.
+// `below` is the number of intermediate certificates between the issuer and the device.
+fn check_issuer(certificate: &X509Certificate<'_>, below: usize) -> Result<(), AuthError> {
+    let invalid = |reason: &str| {
+        AuthError::InvalidCertificate(format!("{} {}", certificate.subject(), reason))
+    };
+
+    let basic_constraints = certificate
+        .basic_constraints()
+        .map_err(|err| AuthError::InvalidCertificate(err.to_string()))?;
+    match basic_constraints.map(|extension| extension.value) {
+        Some(constraints) if constraints.ca => {
+            let max_below = constraints.path_len_constraint.map(|len| len as usize);
+            if max_below.is_some_and(|max_below| below > max_below) {
+                return Err(invalid("exceeds its path length constraint"));
+            }
+        }
+        _ => return Err(invalid("is not a certificate authority")),
+    }
+
+    if !has_key_usage(certificate, KeyUsage::key_cert_sign)? {
+        return Err(invalid("is not allowed to sign certificates"));
+    }
+
+    Ok(())
+}
+
+// Certificates without the key usage extension aren't allowed to do anything
+fn has_key_usage(
+    certificate: &X509Certificate<'_>,
+    usage: impl Fn(&KeyUsage) -> bool,
+) -> Result<bool, AuthError> {
+    let key_usage = certificate
+        .key_usage()
+        .map_err(|err| AuthError::InvalidCertificate(err.to_string()))?;
+    Ok(key_usage.is_some_and(|extension| usage(extension.value)))
+}
+
+fn parse_certificate(der: &[u8]) -> Result<X509Certificate<'_>, AuthError> {
+    X509Certificate::from_der(der)
+        .map(|(_, certificate)| certificate)
+        .map_err(|err| AuthError::InvalidCertificate(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use rcgen::{
+        BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair, KeyUsagePurpose,
+        PKCS_RSA_SHA256,
+    };
+    use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+
+    use super::*;
+
+    struct Authority {
+        key: PKey<Private>,
+        key_pair: KeyPair,
+        params: CertificateParams,
+        certificate: Vec<u8>,
+    }
+
+    impl Authority {
+        fn ca(name: &str, issuer: Option<&Authority>) -> Self {
+            let is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            Self::new(name, issuer, is_ca, KeyUsagePurpose::KeyCertSign)
+        }
+
+        fn device(name: &str, issuer: &Authority) -> Self {
+            let usage = KeyUsagePurpose::DigitalSignature;
+            Self::new(name, Some(issuer), IsCa::ExplicitNoCa, usage)
+        }
+
+        fn new(
+            name: &str,
+            issuer: Option<&Authority>,
+            is_ca: IsCa,
+            key_usage: KeyUsagePurpose,
+        ) -> Self {
+            let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+            let pem = String::from_utf8(key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+            let key_pair = KeyPair::from_pkcs8_pem_and_sign_algo(&pem, &PKCS_RSA_SHA256).unwrap();
+
+            let mut params = CertificateParams::new(vec![]).unwrap();
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, name);
+            params.is_ca = is_ca;
+            params.key_usages = vec![key_usage];
+
+            let certificate = match issuer {
+                Some(issuer) => params.signed_by(&key_pair, &issuer.issuer()),
+                None => params.self_signed(&key_pair),
+            }
+            .unwrap()
+            .der()
+            .to_vec();
+
+            Self {
+                key,
+                key_pair,
+                params,
+                certificate,
+            }
+        }
+
+        fn issuer(&self) -> Issuer<'_, &KeyPair> {
+            Issuer::from_params(&self.params, &self.key_pair)
+        }
+
+        fn sign(&self, data: &[u8]) -> Vec<u8> {
+            let pkcs8 = self.key.private_key_to_pkcs8().unwrap();
+            let key_pair = RsaKeyPair::from_pkcs8(&pkcs8).unwrap();
+            let mut signature = vec![0; key_pair.public().modulus_len()];
+            key_pair
+                .sign(
+                    &RSA_PKCS1_SHA256,
+                    &SystemRandom::new(),
+                    data,
+                    &mut signature,
+                )
+                .unwrap();
+            signature
+        }
+    }
+
+    struct Chain {
+        root: Authority,
+        // Ordered from the device certificate to the root
+        intermediates: Vec<Authority>,
+        device: Authority,
+    }
+
+    impl Chain {
+        fn new() -> Self {
+            let root = Authority::ca("Test Root CA", None);
+            let intermediate = Authority::ca("Test Intermediate CA", Some(&root));
+            let device = Authority::device("Test Device", &intermediate);
+            Self {
+                root,
+                intermediates: vec![intermediate],
+                device,
+            }
+        }
+
+        // The response of a receiver which signs `signed_data` with its device certificate
+        fn response(&self, nonce: &[u8], signed_data: &[u8]) -> DeviceAuthMessage {
+            DeviceAuthMessage {
+                challenge: None,
+                response: Some(AuthResponse {
+                    signature: self.device.sign(signed_data),
+                    client_auth_certificate: self.device.certificate.clone(),
+                    intermediate_certificate: self
+                        .intermediates
+                        .iter()
+                        .map(|intermediate| intermediate.certificate.clone())
+                        .collect(),
+                    signature_algorithm: Some(SignatureAlgorithm::RsassaPkcs1v15.into()),
+                    sender_nonce: Some(nonce.to_vec()),
+                    hash_algorithm: Some(HashAlgorithm::Sha256.into()),
+                    crl: None,
+                }),
+                error: None,
+            }
+        }
+    }
+
+    const NONCE: &[u8] = b"0123456789abcdef";
+    const PEER_CERTIFICATE: &[u8] = b"tls certificate of the receiver";
+
+    fn signed_data(nonce: &[u8], peer_certificate: &[u8]) -> Vec<u8> {
+        [nonce, peer_certificate].concat()
+    }
+
+    #[test]
+    fn verifies_chain() {
+        let chain = Chain::new();
+        let response = chain.response(NONCE, &signed_data(NONCE, PEER_CERTIFICATE));
+
+        let roots = [chain.root.certificate.clone()];
+        let identity = verify(response.clone(), NONCE, PEER_CERTIFICATE, &roots).unwrap();
+        assert_eq!(identity.common_name, "Test Device");
+        assert_eq!(identity.certificate, chain.device.certificate);
+
+        // Roots can also be public keys, e.g. of an `AuthorityKeys` blob
+        let authority_keys = proto::AuthorityKeys {
+            keys: vec![proto::authority_keys::Key {
+                fingerprint: vec![0; 32],
+                public_key: chain.root.key.public_key_to_der().unwrap(),
+            }],
+        };
+        let roots = super::authority_keys(&authority_keys.encode_to_vec()).unwrap();
+        verify(response, NONCE, PEER_CERTIFICATE, &roots).unwrap();
+    }
+
+    #[test]
+    fn parses_bundled_roots() {
+        for root in cast_roots() {
+            X509Certificate::from_der(&root).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_untrusted_chain() {
+        let chain = Chain::new();
+        let response = chain.response(NONCE, &signed_data(NONCE, PEER_CERTIFICATE));
+
+        let other_root = Authority::ca("Other Root CA", None);
+        let roots = [other_root.certificate];
+        let result = verify(response, NONCE, PEER_CERTIFICATE, &roots);
+        assert!(matches!(result, Err(AuthError::UntrustedChain)));
+    }
+
+    #[test]
+    fn rejects_nonce_mismatch() {
+        let chain = Chain::new();
+        let other_nonce = b"fedcba9876543210";
+        let response = chain.response(other_nonce, &signed_data(other_nonce, PEER_CERTIFICATE));
+
+        let roots = [chain.root.certificate.clone()];
+        let result = verify(response, NONCE, PEER_CERTIFICATE, &roots);
+        assert!(matches!(result, Err(AuthError::NonceMismatch)));
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let chain = Chain::new();
+        // Signed the certificate of another connection, e.g. one intercepted by a proxy
+        let response = chain.response(NONCE, &signed_data(NONCE, b"tls certificate of a proxy"));
+
+        let roots = [chain.root.certificate.clone()];
+        let result = verify(response, NONCE, PEER_CERTIFICATE, &roots);
+        assert!(matches!(result, Err(AuthError::InvalidSignature)));
+    }
+
+    // Reason of the `InvalidCertificate` error the chain gets rejected with
+    fn invalid_certificate(chain: &Chain) -> String {
+        let response = chain.response(NONCE, &signed_data(NONCE, PEER_CERTIFICATE));
+        let roots = [chain.root.certificate.clone()];
+        match verify(response, NONCE, PEER_CERTIFICATE, &roots) {
+            Err(AuthError::InvalidCertificate(reason)) => reason,
+            result => panic!("expected an invalid certificate, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_device_issuing_certificates() {
+        // A device certificate of the trusted tree signing another device certificate
+        let mut chain = Chain::new();
+        let device = Authority::device("Forged Device", &chain.device);
+        let issuer = std::mem::replace(&mut chain.device, device);
+        chain.intermediates.insert(0, issuer);
+
+        let reason = invalid_certificate(&chain);
+        assert!(reason.contains("not a certificate authority"), "{reason}");
+    }
+
+    #[test]
+    fn rejects_issuer_without_key_cert_sign() {
+        let root = Authority::ca("Test Root CA", None);
+        let is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let usage = KeyUsagePurpose::DigitalSignature;
+        let intermediate = Authority::new("Test Intermediate CA", Some(&root), is_ca, usage);
+        let device = Authority::device("Test Device", &intermediate);
+        let chain = Chain {
+            root,
+            intermediates: vec![intermediate],
+            device,
+        };
+
+        let reason = invalid_certificate(&chain);
+        assert!(
+            reason.contains("not allowed to sign certificates"),
+            "{reason}"
+        );
+    }
+
+    #[test]
+    fn rejects_device_without_digital_signature() {
+        let mut chain = Chain::new();
+        let issuer = Some(&chain.intermediates[0]);
+        let usage = KeyUsagePurpose::KeyEncipherment;
+        chain.device = Authority::new("Test Device", issuer, IsCa::ExplicitNoCa, usage);
+
+        let reason = invalid_certificate(&chain);
+        assert!(reason.contains("not allowed to sign data"), "{reason}");
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+use futures_util::{stream, Stream};
+use mdns_sd::{ResolvedService, ServiceDaemon, ServiceEvent};
+
+use crate::namespace::media::DeviceCapabilities;
+use crate::Error;
+
+/// DNS-SD service type announced by all cast receivers
+pub const SERVICE_TYPE: &str = "_googlecast._tcp.local.";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    /// Full DNS-SD service instance name, used to identify the device across events
+    pub fullname: String,
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+
+    /// TXT record `id`
+    pub id: String,
+    /// TXT record `fn`
+    pub friendly_name: String,
+    /// TXT record `md`
+    pub model_name: String,
+    /// TXT record `ca`
+    pub capabilities: DeviceCapabilities,
+    /// TXT record `rs`
+    pub status_text: Option<String>,
+    /// TXT record `ve`
+    pub version: Option<String>,
+    /// TXT record `ic`
+    pub icon_path: Option<String>,
+}
+
+impl DiscoveredDevice {
+    /// First known socket address of the device, IPv4 addresses are preferred.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.addresses
+            .iter()
+            .find(|addr| addr.is_ipv4())
+            .or(self.addresses.first())
+            .map(|addr| SocketAddr::new(*addr, self.port))
+    }
+
+    fn from_service(service: &ResolvedService) -> Self {
+        let txt = |key: &str| service.get_property_val_str(key).map(str::to_string);
+
+        let mut addresses: Vec<IpAddr> = service
+            .get_addresses()
+            .iter()
+            .map(|addr| addr.to_ip_addr())
+            .collect();
+        addresses.sort();
+
+        Self {
+            fullname: service.get_fullname().to_string(),
+            hostname: service.get_hostname().to_string(),
+            addresses,
+            port: service.get_port(),
+            id: txt("id").unwrap_or_default(),
+            friendly_name: txt("fn").unwrap_or_default(),
+            model_name: txt("md").unwrap_or_default(),
+            capabilities: txt("ca")
+                .and_then(|ca| ca.parse().ok())
+                .map(DeviceCapabilities::from_bits_retain)
+                .unwrap_or_default(),
+            status_text: txt("rs").filter(|rs| !rs.is_empty()),
+            version: txt("ve"),
+            icon_path: txt("ic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryEvent {
+    Added(DiscoveredDevice),
+    /// A known device changed its announcement, e.g. the `rs` status text
+    Updated(DiscoveredDevice),
+    Removed(DiscoveredDevice),
+}
+
+pub struct Discovery {
+    daemon: ServiceDaemon,
+}
+
+impl Discovery {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            daemon: ServiceDaemon::new()?,
+        })
+    }
+
+    /// Uses a non-standard mDNS port, which is mostly useful for testing
+    /// against a local responder without interfering with the system one.
+    pub fn with_port(port: u16) -> Result<Self, Error> {
+        Ok(Self {
+            daemon: ServiceDaemon::new_with_port(port)?,
+        })
+    }
+
+    /// Browses for cast receivers. The stream keeps running until the
+    /// [`Discovery`] gets dropped.
+    pub fn browse(&self) -> Result<impl Stream<Item = DiscoveryEvent>, Error> {
+        let receiver = self.daemon.browse(SERVICE_TYPE)?;
+        let devices: HashMap<String, DiscoveredDevice> = HashMap::new();
+
+        Ok(stream::unfold(
+            (receiver, devices),
+            |(receiver, mut devices)| async move {
+                while let Ok(event) = receiver.recv_async().await {
+                    let event = match event {
+                        ServiceEvent::ServiceResolved(service) => {
+                            let device = DiscoveredDevice::from_service(&service);
+                            match devices.insert(device.fullname.clone(), device.clone()) {
+                                None => DiscoveryEvent::Added(device),
+                                Some(old) if old != device => DiscoveryEvent::Updated(device),
+                                Some(_) => continue,
+                            }
+                        }
+                        ServiceEvent::ServiceRemoved(_, fullname) => {
+                            match devices.remove(&fullname) {
+                                Some(device) => DiscoveryEvent::Removed(device),
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    };
+
+                    debug!("[DISCOVERY] {:?}", event);
+                    return Some((event, (receiver, devices)));
+                }
+
+                None
+            },
+        ))
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        let _ = self.daemon.shutdown();
+    }
+}
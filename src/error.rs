@@ -1,16 +1,19 @@
-use thiserror::Error;
+use std::fmt;
 
-use crate::namespace::media::ErrorReason;
+use thiserror::Error;
 
-use super::Response;
+use crate::namespace::media::{ErrorResponseData, MediaCategory};
+use crate::namespace::receiver::{InvalidRequestResponse, LaunchErrorResponse};
+use crate::namespace::NamespaceUrn;
+use crate::Response;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Input/Output error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("Input/Output error: {0}")]
-    AddrParseError(#[from] std::net::AddrParseError),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
 
     #[error("TLS error: {0}")]
     Tls(#[from] async_native_tls::Error),
@@ -18,45 +21,199 @@ pub enum Error {
     #[error("Decode error: {0}")]
     Decode(#[from] prost::DecodeError),
 
+    #[error("Discovery error: {0}")]
+    Discovery(#[from] mdns_sd::Error),
+
     #[error("Deserialize error: {0}")]
     Deserialize(#[from] serde_json::Error),
 
     #[error("Receive error: {0}")]
     Receive(#[from] async_channel::RecvError),
 
-    #[error("Send error: {0}")]
-    Send(#[from] async_channel::SendError<Response>),
-
-    #[error("Did not receive request response")]
-    ResponseTimeout,
+    #[error("Did not receive request response: {0}")]
+    ResponseTimeout(Box<RequestContext>),
 
     #[error("Not connected with receiver")]
     NoConnection,
 
-    #[error("Unable to launch app: {0}")]
-    LaunchError(String),
+    #[error("Receiver did not respond to heartbeat")]
+    HeartbeatTimeout,
 
     #[error("Unsupported Namespace")]
     UnsupportedNamespace,
 
+    #[error("Application is not running anymore")]
+    AppNotRunning,
+
+    #[error("No media session")]
+    NoMediaSession,
+
+    #[error("Receiver is not able to play {0:?} media")]
+    UnsupportedMediaCategory(MediaCategory),
+
+    #[error("{0}")]
+    Request(Box<RequestError>),
+
+    #[error("Unexpected response: {}", ResponseContext(.0))]
+    UnexpectedResponse(Box<Response>),
+
+    #[error("Did not receive a matching response: {0}")]
+    NoResponse(Box<RequestContext>),
+
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+
+    #[error("Frame of {0} bytes exceeds the maximum frame size of {max} bytes", max = crate::client::MAX_FRAME_SIZE)]
+    FrameTooLarge(usize),
+
+    #[error("Message of {0} bytes exceeds the maximum message size of {max} bytes", max = crate::client::MAX_MESSAGE_SIZE)]
+    MessageTooLarge(usize),
+}
+
+/// A request the receiver responded to with an error
+#[derive(Error, Debug, Clone)]
+#[error("{kind}: {}", ResponseContext(&self.response))]
+pub struct RequestError {
+    pub kind: RequestErrorKind,
+    /// The complete error response, its source is the transport id the request was sent to
+    pub response: Response,
+}
+
+impl RequestError {
+    pub fn namespace(&self) -> &NamespaceUrn {
+        &self.response.namespace
+    }
+
+    pub fn request_id(&self) -> Option<u32> {
+        self.response.request_id
+    }
+
+    /// Transport id the failed request was sent to
+    pub fn transport_id(&self) -> &str {
+        &self.response.source_id
+    }
+}
+
+/// The request which didn't get the expected response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub namespace: NamespaceUrn,
+    /// `None` for messages which aren't matched by request id, e.g. pings
+    pub request_id: Option<u32>,
+    /// Transport id the request was sent to
+    pub destination_id: String,
+}
+
+impl fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to {}", self.namespace, self.destination_id)?;
+        if let Some(request_id) = self.request_id {
+            write!(f, " (request {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    pub(crate) fn response_timeout(
+        namespace: NamespaceUrn,
+        request_id: Option<u32>,
+        destination_id: impl Into<String>,
+    ) -> Self {
+        Self::ResponseTimeout(Box::new(RequestContext {
+            namespace,
+            request_id,
+            destination_id: destination_id.into(),
+        }))
+    }
+
+    /// The response is missing the expected content, e.g. an empty media status
+    pub(crate) fn no_response(response: &Response) -> Self {
+        Self::NoResponse(Box::new(RequestContext {
+            namespace: response.namespace.clone(),
+            request_id: response.request_id,
+            destination_id: response.source_id.clone(),
+        }))
+    }
+
+    pub(crate) fn request(kind: RequestErrorKind, response: &Response) -> Self {
+        Self::Request(Box::new(RequestError {
+            kind,
+            response: response.clone(),
+        }))
+    }
+
+    pub(crate) fn unexpected_response(response: Response) -> Self {
+        Self::UnexpectedResponse(Box::new(response))
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum RequestErrorKind {
+    #[error("Unable to launch app: {}", .0.reason)]
+    LaunchError(LaunchErrorResponse),
+
+    #[error("Invalid Request: {}", .0.reason)]
+    InvalidRequest(InvalidRequestResponse),
+
     #[error("Media Channel Error: {0}")]
     MediaError(MediaError),
-
-    #[error("Did not receive a matching response")]
-    NoResponse,
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum MediaError {
-    #[error("Invalid Request")]
-    InvalidRequest(ErrorReason),
+    #[error("Invalid Request ({:?})", .0.reason)]
+    InvalidRequest(ErrorResponseData),
 
     #[error("Invalid Player State")]
-    InvalidPlayerState,
+    InvalidPlayerState(ErrorResponseData),
 
-    #[error("Load Failed")]
-    LoadFailed,
+    #[error("Load Failed ({:?})", .0.detailed_error_code)]
+    LoadFailed(ErrorResponseData),
 
     #[error("Load Cancelled")]
-    LoadCancelled,
+    LoadCancelled(ErrorResponseData),
+
+    #[error("Error ({:?}, {:?})", .0.reason, .0.detailed_error_code)]
+    Error(ErrorResponseData),
+}
+
+// Where a response came from, for error messages
+struct ResponseContext<'a>(&'a Response);
+
+impl fmt::Display for ResponseContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} from {}", self.0.namespace, self.0.source_id)?;
+        if let Some(request_id) = self.0.request_id {
+            write!(f, " (request {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Device authentication failed: {0}")]
+    Request(#[from] Error),
+
+    #[error("Receiver did not present a TLS certificate")]
+    NoPeerCertificate,
+
+    #[error("Receiver rejected the challenge: {0}")]
+    Rejected(String),
+
+    #[error("Invalid challenge response")]
+    InvalidResponse,
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Certificate chain does not lead to a trusted root")]
+    UntrustedChain,
+
+    #[error("Challenge response does not contain the sent nonce")]
+    NonceMismatch,
+
+    #[error("Invalid challenge response signature")]
+    InvalidSignature,
 }
@@ -7,21 +7,43 @@ extern crate serde_with;
 #[macro_use]
 extern crate derive_builder;
 
+pub mod discovery;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod namespace;
 mod proto;
 
+mod addr;
 mod app;
 mod client;
+mod config;
+mod device_auth;
 mod error;
 mod media_controller;
+mod multizone_controller;
+mod namespace_channel;
 mod payload;
+mod queue_controller;
 mod receiver;
 mod shared;
+mod transfer;
 
+pub use addr::{CastAddr, ToCastAddr, DEFAULT_PORT};
 pub use app::{App, AppId};
-pub use client::{Client, Response};
-pub use error::Error;
+pub use client::{
+    Client, RawMessage, RawPayload, Response, DEFAULT_SENDER_ID, MAX_FRAME_SIZE, MAX_MESSAGE_SIZE,
+};
+pub use config::{
+    HeartbeatConfig, HeartbeatConfigBuilder, ReceiverConfig, ReceiverConfigBuilder,
+    ReconnectPolicy, ReconnectPolicyBuilder,
+};
+pub use device_auth::{authority_keys, cast_roots, DeviceIdentity};
+pub use error::{AuthError, Error, MediaError, RequestContext, RequestError, RequestErrorKind};
 pub use media_controller::MediaController;
+pub use multizone_controller::MultizoneController;
+pub use namespace_channel::NamespaceChannel;
 pub use payload::Payload;
-pub use receiver::Receiver;
+pub use queue_controller::QueueController;
+pub use receiver::{AppEvent, ConnectionState, DisconnectReason, Receiver};
 pub use shared::*;
+pub use transfer::transfer_session;
@@ -1,16 +1,84 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use async_broadcast::InactiveReceiver;
+use futures_util::Stream;
 use smol::lock::Mutex;
 
+use crate::error::{MediaError, RequestErrorKind};
 use crate::namespace::media::*;
-use crate::{App, Error, Payload, Receiver, Response};
+use crate::receiver::{event_channel, spawn_event_sync};
+use crate::{App, Error, Payload, Receiver, Response, Volume};
+
+#[derive(Debug)]
+struct CachedStatus {
+    status: MediaStatus,
+    // Used to estimate the playback position
+    received: Instant,
+}
+
+#[derive(Debug)]
+struct StatusCache {
+    status: Mutex<Option<CachedStatus>>,
+    // `None` once the media session ended
+    events: async_broadcast::Sender<Option<MediaStatus>>,
+    _events_rx: InactiveReceiver<Option<MediaStatus>>,
+}
+
+impl StatusCache {
+    fn new() -> Self {
+        let (events, events_rx) = event_channel();
+
+        Self {
+            status: Mutex::default(),
+            events,
+            _events_rx: events_rx,
+        }
+    }
+
+    async fn process(&self, response: &Response) {
+        let Payload::Media(Media::MediaStatus(response_data)) = &response.payload else {
+            return;
+        };
+
+        let Some(status) = response_data.status.first() else {
+            // No media session anymore
+            if self.status.lock().await.take().is_some() {
+                let _ = self.events.try_broadcast(None);
+            }
+            return;
+        };
+
+        let mut status = status.clone();
+        let mut cached = self.status.lock().await;
+
+        // Receivers only include the media information when it changed
+        if let Some(cached) = cached.as_ref() {
+            if status.media.is_none() && status.media_session_id == cached.status.media_session_id {
+                status.media = cached.status.media.clone();
+            }
+        }
+
+        *cached = Some(CachedStatus {
+            status: status.clone(),
+            received: Instant::now(),
+        });
+        let _ = self.events.try_broadcast(Some(status));
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct MediaController {
     app: App,
     receiver: Receiver,
 
-    media_session_id: Arc<Mutex<i32>>,
+    // Latest status, updated by responses and unsolicited status messages
+    status: Arc<StatusCache>,
+    // Receiver connection the status was last requested on
+    connection_id: Arc<Mutex<u32>>,
+
+    // Overrides the default request timeout of the receiver
+    timeout: Option<Duration>,
 }
 
 impl MediaController {
@@ -22,45 +90,123 @@ impl MediaController {
             return Err(Error::UnsupportedNamespace);
         }
 
-        Ok(Self {
+        let controller = Self {
             app,
             receiver,
-            media_session_id: Arc::default(),
-        })
+            status: Arc::new(StatusCache::new()),
+            connection_id: Arc::default(),
+            timeout: None,
+        };
+        let events = controller
+            .receiver
+            .transport_events(controller.app.transport_id.clone());
+        spawn_event_sync(
+            events,
+            Arc::downgrade(&controller.status),
+            |status, response| async move { status.process(&response).await },
+        );
+
+        Ok(controller)
+    }
+
+    /// Returns a controller for the same media session, whose requests
+    /// wait up to `timeout` for a response, e.g. to let UI actions fail fast.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
     }
 
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub(crate) fn receiver(&self) -> &Receiver {
+        &self.receiver
+    }
+
+    /// The latest known status of the media session, without sending a request
+    pub async fn status(&self) -> Option<MediaStatus> {
+        let status = self.status.status.lock().await;
+        status.as_ref().map(|cached| cached.status.clone())
+    }
+
+    /// Every status update of the media session, e.g. when playback got paused by another sender.
+    /// Yields `None` once the receiver reports no media session anymore, e.g. after the app stopped it.
+    pub fn watch_status(&self) -> impl Stream<Item = Option<MediaStatus>> + Unpin + Send {
+        self.status.events.new_receiver()
+    }
+
+    /// Playback position in seconds, which advances with the playback rate
+    /// since the latest status update while playing.
+    pub async fn estimated_position(&self) -> Option<f64> {
+        let status = self.status.status.lock().await;
+        let cached = status.as_ref()?;
+
+        let mut position = cached.status.current_time;
+        if matches!(cached.status.player_state, PlayerState::Playing) {
+            let elapsed = cached.received.elapsed().as_secs_f64();
+            position += elapsed * cached.status.playback_rate;
+        }
+
+        // Live streams don't have a duration
+        let duration = cached
+            .status
+            .media
+            .as_ref()
+            .and_then(|media| media.duration);
+        if let Some(duration) = duration.filter(|duration| *duration > 0.0) {
+            position = position.min(duration);
+        }
+
+        Some(position.max(0.0))
+    }
+
+    /// Fails with [`Error::UnsupportedMediaCategory`] when loading videos on audio-only receivers.
+    /// Capabilities are only known when connecting to a [`DiscoveredDevice`](crate::discovery::DiscoveredDevice)
+    /// or to a [`CastAddr::with_capabilities`](crate::CastAddr::with_capabilities), receivers
+    /// connected by IP address or hostname never reject videos.
     pub async fn load(&self, media: MediaInformation) -> Result<(), Error> {
-        let response = self
+        self.load_with(LoadRequestData {
+            media,
+            autoplay: Some(true),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Same as [`MediaController::load`], e.g. to start at a position or with specific tracks
+    pub async fn load_with(&self, request: LoadRequestData) -> Result<(), Error> {
+        let audio_only = self
             .receiver
-            .send_request(
-                &self.app,
-                Media::Load(LoadRequestData {
-                    media,
-                    autoplay: Some(true),
-                    ..Default::default()
-                }),
-            )
-            .await?;
+            .capabilities()
+            .await
+            .is_some_and(|capabilities| capabilities.is_audio_only());
+        if audio_only && request.media.category() == Some(MediaCategory::Video) {
+            return Err(Error::UnsupportedMediaCategory(MediaCategory::Video));
+        }
+
+        let response = self.send_request(Media::Load(request)).await?;
 
         Self::handle_error(&response)?;
 
-        if let Payload::Media(Media::MediaStatus(response_data)) = response.payload {
-            *self.media_session_id.lock().await = response_data.first().media_session_id;
-            Ok(())
-        } else {
-            Err(Error::NoResponse)
+        match &response.payload {
+            Payload::Media(Media::MediaStatus(response_data)) => {
+                response_data
+                    .first()
+                    .ok_or_else(|| Error::no_response(&response))?;
+                Ok(())
+            }
+            _ => Err(Error::unexpected_response(response)),
         }
     }
 
     pub async fn start(&self) -> Result<(), Error> {
         let response = self
-            .receiver
-            .send_request(
-                &self.app,
-                Media::Play(RequestData {
-                    media_session_id: Some(*self.media_session_id.lock().await),
-                }),
-            )
+            .send_request(Media::Play(RequestData {
+                media_session_id: Some(self.media_session_id().await?),
+            }))
             .await?;
 
         Self::handle_error(&response)?;
@@ -69,13 +215,9 @@ impl MediaController {
 
     pub async fn stop(&self) -> Result<(), Error> {
         let response = self
-            .receiver
-            .send_request(
-                &self.app,
-                Media::Stop(RequestData {
-                    media_session_id: Some(*self.media_session_id.lock().await),
-                }),
-            )
+            .send_request(Media::Stop(RequestData {
+                media_session_id: Some(self.media_session_id().await?),
+            }))
             .await?;
 
         Self::handle_error(&response)?;
@@ -84,40 +226,236 @@ impl MediaController {
 
     pub async fn pause(&self) -> Result<(), Error> {
         let response = self
-            .receiver
-            .send_request(
-                &self.app,
-                Media::Pause(RequestData {
-                    media_session_id: Some(*self.media_session_id.lock().await),
-                }),
-            )
+            .send_request(Media::Pause(RequestData {
+                media_session_id: Some(self.media_session_id().await?),
+            }))
             .await?;
 
         Self::handle_error(&response)?;
         Ok(())
     }
 
-    fn handle_error(response: &Response) -> Result<(), Error> {
-        if let Payload::Media(Media::InvalidRequest(err)) = &response.payload {
-            return Err(Error::MediaError(crate::error::MediaError::InvalidRequest(
-                err.reason.clone(),
-            )));
+    /// Seeks to `position` in seconds. Without a `resume_state` the
+    /// player state stays the same as before seeking.
+    pub async fn seek(
+        &self,
+        position: f64,
+        resume_state: Option<SeekResumeState>,
+    ) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::Seek(SeekRequestData {
+            current_time: Some(position),
+            media_session_id: Some(self.media_session_id().await?),
+            relative_time: None,
+            resume_state,
+        }))
+        .await
+    }
+
+    /// Seeks `offset` seconds forward, or backwards if negative
+    pub async fn seek_relative(
+        &self,
+        offset: f64,
+        resume_state: Option<SeekResumeState>,
+    ) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::Seek(SeekRequestData {
+            current_time: None,
+            media_session_id: Some(self.media_session_id().await?),
+            relative_time: Some(offset),
+            resume_state,
+        }))
+        .await
+    }
+
+    /// Sets the playback rate, `1.0` being normal speed
+    pub async fn set_playback_rate(&self, rate: f64) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::SetPlaybackRate(SetPlaybackRateRequestData {
+            media_session_id: Some(self.media_session_id().await?),
+            playback_rate: Some(rate),
+            relative_playback_rate: None,
+        }))
+        .await
+    }
+
+    /// Multiplies the current playback rate by `factor`
+    pub async fn set_relative_playback_rate(&self, factor: f64) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::SetPlaybackRate(SetPlaybackRateRequestData {
+            media_session_id: Some(self.media_session_id().await?),
+            playback_rate: None,
+            relative_playback_rate: Some(factor),
+        }))
+        .await
+    }
+
+    /// Sets the stream volume between `0.0` and `1.0`, unlike
+    /// [`Receiver::set_volume`] which changes the device volume.
+    pub async fn set_volume(&self, level: f64) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::SetVolume(VolumeRequestData {
+            media_session_id: Some(self.media_session_id().await?),
+            volume: Volume {
+                level: Some(level),
+                ..Default::default()
+            },
+        }))
+        .await
+    }
+
+    /// Mutes or unmutes the stream
+    pub async fn set_muted(&self, muted: bool) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::SetVolume(VolumeRequestData {
+            media_session_id: Some(self.media_session_id().await?),
+            volume: Volume {
+                muted: Some(muted),
+                ..Default::default()
+            },
+        }))
+        .await
+    }
+
+    /// Activates the tracks with the given ids, see [`MediaInformation::tracks`].
+    /// All other tracks get deactivated.
+    pub async fn set_active_tracks(&self, track_ids: Vec<i32>) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::EditTracksInfo(EditTracksInfoRequestData {
+            active_track_ids: Some(track_ids),
+            media_session_id: Some(self.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Enables or disables text tracks. When enabling, the receiver
+    /// picks a track matching `language` if given.
+    pub async fn set_text_tracks_enabled(
+        &self,
+        enabled: bool,
+        language: Option<String>,
+    ) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::EditTracksInfo(EditTracksInfoRequestData {
+            enable_text_tracks: Some(enabled),
+            language,
+            media_session_id: Some(self.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    pub async fn set_text_track_style(&self, style: TextTrackStyle) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::EditTracksInfo(EditTracksInfoRequestData {
+            media_session_id: Some(self.media_session_id().await?),
+            text_track_style: Some(style),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Switches to the audio track matching `language`
+    pub async fn set_audio_language(&self, language: String) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::EditAudioTracks(EditAudioTracksRequestData {
+            is_suggested_language: None,
+            language: Some(language),
+            media_session_id: Some(self.media_session_id().await?),
+        }))
+        .await
+    }
+
+    /// Requests the current status, instead of relying on the cached [`MediaController::status`]
+    pub(crate) async fn fetch_status(&self) -> Result<Option<MediaStatus>, Error> {
+        let response = self
+            .send_request(Media::GetStatus(GetStatusRequestData::default()))
+            .await?;
+        Self::handle_error(&response)?;
+
+        Ok(self.status().await)
+    }
+
+    /// Captures the media session, so that it can be continued on another receiver
+    /// with [`MediaController::resume_session`]. Only supported by apps which announce
+    /// [`Command::StreamTransfer`].
+    pub async fn store_session(&self) -> Result<SessionState, Error> {
+        let response = self
+            .send_request(Media::StoreSession(StoreSessionRequestData {
+                media_session_id: Some(self.media_session_id().await?),
+            }))
+            .await?;
+        Self::handle_error(&response)?;
+
+        if let Payload::Media(Media::SessionState(data)) = response.payload {
+            return Ok(data.session_state);
         }
 
-        if let Payload::Media(Media::InvalidPlayerState) = response.payload {
-            return Err(Error::MediaError(
-                crate::error::MediaError::InvalidPlayerState,
-            ));
+        Err(Error::unexpected_response(response))
+    }
+
+    /// Continues a media session stored with [`MediaController::store_session`]
+    pub async fn resume_session(&self, session_state: SessionState) -> Result<MediaStatus, Error> {
+        self.send_media_request(Media::ResumeSession(ResumeSessionRequestData {
+            session_state,
+        }))
+        .await
+    }
+
+    /// Returns the id of the current media session from the cached status, which
+    /// follows sessions started by other senders. The status gets requested again
+    /// after the receiver connection got re-established.
+    pub(crate) async fn media_session_id(&self) -> Result<i32, Error> {
+        if *self.connection_id.lock().await != self.receiver.connection_id().await {
+            self.fetch_status().await?;
         }
 
-        if let Payload::Media(Media::LoadFailed) = response.payload {
-            return Err(Error::MediaError(crate::error::MediaError::LoadFailed));
+        self.status()
+            .await
+            .map(|status| status.media_session_id)
+            .ok_or(Error::NoMediaSession)
+    }
+
+    /// Sends the request, and returns the media status the receiver responded with
+    pub(crate) async fn send_media_request(&self, payload: Media) -> Result<MediaStatus, Error> {
+        let response = self.send_request(payload).await?;
+        Self::handle_error(&response)?;
+
+        if let Payload::Media(Media::MediaStatus(response_data)) = &response.payload {
+            response_data
+                .status
+                .first()
+                .cloned()
+                .ok_or_else(|| Error::no_response(&response))
+        } else {
+            Err(Error::unexpected_response(response))
         }
+    }
+
+    pub(crate) async fn send_request(&self, payload: Media) -> Result<Response, Error> {
+        let response = match self.timeout {
+            Some(timeout) => {
+                self.receiver
+                    .send_request_with_timeout(&self.app, payload, timeout)
+                    .await
+            }
+            None => self.receiver.send_request(&self.app, payload).await,
+        }?;
 
-        if let Payload::Media(Media::LoadCancelled) = response.payload {
-            return Err(Error::MediaError(crate::error::MediaError::LoadCancelled));
+        if let Payload::Media(Media::MediaStatus(_)) = &response.payload {
+            // The cache is up to date for the current connection
+            *self.connection_id.lock().await = self.receiver.connection_id().await;
         }
+        self.status.process(&response).await;
+        Ok(response)
+    }
 
-        Ok(())
+    pub(crate) fn handle_error(response: &Response) -> Result<(), Error> {
+        let error = match &response.payload {
+            Payload::Media(Media::InvalidRequest(data)) => MediaError::InvalidRequest(data.clone()),
+            Payload::Media(Media::InvalidPlayerState(data)) => {
+                MediaError::InvalidPlayerState(data.clone())
+            }
+            Payload::Media(Media::LoadFailed(data)) => MediaError::LoadFailed(data.clone()),
+            Payload::Media(Media::LoadCancelled(data)) => MediaError::LoadCancelled(data.clone()),
+            Payload::Media(Media::Error(data)) => MediaError::Error(data.clone()),
+            _ => return Ok(()),
+        };
+
+        Err(Error::request(
+            RequestErrorKind::MediaError(error),
+            response,
+        ))
     }
 }
@@ -0,0 +1,897 @@
+//! In-process cast receiver for testing without a physical device.
+//!
+//! The [`MockReceiver`] listens on localhost with a self-signed certificate and implements
+//! the connection, heartbeat, receiver and media namespaces. Its behaviour can be scripted,
+//! e.g. by delaying responses or replacing them with errors.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_native_tls::{TlsAcceptor, TlsStream};
+use async_net::{TcpListener, TcpStream};
+use futures_util::io::WriteHalf;
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use smol::lock::Mutex;
+
+use crate::client::{decode_message, encode_frames, encode_message, read_message};
+use crate::namespace::connection::Connection;
+use crate::namespace::heartbeat::Heartbeat;
+use crate::namespace::media::*;
+use crate::namespace::multizone::*;
+use crate::namespace::receiver::{self, *};
+use crate::namespace::NamespaceUrn;
+use crate::{App, AppId, Error, Payload, Response, Volume};
+
+#[derive(Clone)]
+struct MockConnection {
+    tcp_stream: TcpStream,
+    write_stream: Arc<Mutex<WriteHalf<TlsStream<TcpStream>>>>,
+}
+
+impl MockConnection {
+    async fn send(
+        &self,
+        source_id: String,
+        destination_id: String,
+        payload: Payload,
+        request_id: Option<u32>,
+    ) {
+        let result = match encode_message(source_id, destination_id, &payload, request_id)
+            .and_then(|msg| encode_frames(&msg))
+        {
+            Ok(frames) => {
+                let mut write_stream = self.write_stream.lock().await;
+                write_stream.write_all(&frames).await.map_err(Error::from)
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            debug!("[MOCK] Unable to send message: {}", err);
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    // Apps which can get launched, with the namespaces they support
+    available_apps: HashMap<String, Vec<NamespaceUrn>>,
+    running_apps: Vec<App>,
+    volume: Volume,
+    media_status: Option<MediaStatus>,
+    queue: Vec<QueueItem>,
+    multizone_members: Vec<Device>,
+    // Unsolicited messages which get sent to all senders after the response
+    pending_broadcasts: Vec<Payload>,
+    // Used for session, transport and media session ids
+    next_id: i32,
+
+    delays: HashMap<String, Duration>,
+    // Scripted responses, `None` swallows the request
+    scripted: HashMap<String, VecDeque<Option<Payload>>>,
+    unresponsive: bool,
+
+    received: Vec<Response>,
+    connections: Vec<MockConnection>,
+}
+
+pub struct MockReceiver {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    _task: smol::Task<()>,
+}
+
+impl MockReceiver {
+    /// Starts listening on a random localhost port, see [`MockReceiver::addr`]
+    pub async fn start() -> Result<Self, Error> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .map_err(std::io::Error::other)?;
+        let identity = async_native_tls::Identity::from_pkcs8(
+            cert.cert.pem().as_bytes(),
+            cert.signing_key.serialize_pem().as_bytes(),
+        )?;
+        let acceptor: TlsAcceptor = native_tls::TlsAcceptor::new(identity)?.into();
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(MockState {
+            available_apps: HashMap::from([
+                (
+                    AppId::DefaultMediaReceiver.to_string(),
+                    vec![NamespaceUrn::Media],
+                ),
+                (AppId::Backdrop.to_string(), vec![]),
+            ]),
+            volume: Volume {
+                control_type: Some("attenuation".into()),
+                muted: Some(false),
+                level: Some(1.0),
+            },
+            next_id: 1,
+            ..Default::default()
+        }));
+
+        let s = state.clone();
+        let task = smol::spawn(async move {
+            while let Ok((tcp_stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let state = s.clone();
+
+                smol::spawn(async move {
+                    match acceptor.accept(tcp_stream.clone()).await {
+                        Ok(tls_stream) => Self::serve(state, tcp_stream, tls_stream).await,
+                        Err(err) => debug!("[MOCK] TLS handshake failed: {}", err),
+                    }
+                })
+                .detach();
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            _task: task,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Allows launching the app, which then supports the given namespaces
+    pub async fn add_app(&self, app_id: AppId, namespaces: Vec<NamespaceUrn>) {
+        let mut state = self.state.lock().await;
+        state.available_apps.insert(app_id.to_string(), namespaces);
+    }
+
+    pub async fn running_apps(&self) -> Vec<App> {
+        self.state.lock().await.running_apps.clone()
+    }
+
+    pub async fn media_status(&self) -> Option<MediaStatus> {
+        self.state.lock().await.media_status.clone()
+    }
+
+    /// Replaces the current media status and broadcasts it to all senders,
+    /// like a receiver does when the playback state changes on its own.
+    pub async fn set_media_status(&self, status: MediaStatus) {
+        let source_id = {
+            let mut state = self.state.lock().await;
+            state.media_status = Some(status.clone());
+            state
+                .running_apps
+                .iter()
+                .find(|app| app.namespaces.contains(&NamespaceUrn::Media))
+                .map(|app| app.transport_id.clone())
+        };
+
+        if let Some(source_id) = source_id {
+            let payload = Media::MediaStatus(ResponseData {
+                status: vec![status],
+            });
+            self.broadcast(&source_id, payload).await;
+        }
+    }
+
+    /// Sends an unsolicited message to all connected senders
+    pub async fn broadcast<P: Into<Payload>>(&self, source_id: &str, payload: P) {
+        self.send_to(source_id, "*", payload).await;
+    }
+
+    /// Sends an unsolicited message to a single sender id, on all connections
+    pub async fn send_to<P: Into<Payload>>(
+        &self,
+        source_id: &str,
+        destination_id: &str,
+        payload: P,
+    ) {
+        let payload: Payload = payload.into();
+        let connections = self.state.lock().await.connections.clone();
+        for connection in connections {
+            connection
+                .send(
+                    source_id.into(),
+                    destination_id.into(),
+                    payload.clone(),
+                    Some(0),
+                )
+                .await;
+        }
+    }
+
+    /// Stops the app like another sender would, closing the virtual connections to it
+    pub async fn stop_app(&self, app: &App) {
+        self.state
+            .lock()
+            .await
+            .running_apps
+            .retain(|running| running.session_id != app.session_id);
+        self.broadcast(&app.transport_id, Connection::Close).await;
+    }
+
+    /// Closes the virtual connection of a single sender id to the app, which keeps running
+    pub async fn close_connection(&self, app: &App, sender_id: &str) {
+        let connections = self.state.lock().await.connections.clone();
+        for connection in connections {
+            let payload = Connection::Close.into();
+            connection
+                .send(app.transport_id.clone(), sender_id.into(), payload, None)
+                .await;
+        }
+    }
+
+    /// Adds a member to the cast group, or replaces the one with the same device id
+    pub async fn add_multizone_member(&self, device: Device) {
+        let payload = {
+            let mut state = self.state.lock().await;
+            let members = &mut state.multizone_members;
+            match members.iter_mut().find(|d| d.device_id == device.device_id) {
+                Some(existing) => {
+                    *existing = device.clone();
+                    Multizone::DeviceUpdated(DeviceResponse { device })
+                }
+                None => {
+                    members.push(device.clone());
+                    Multizone::DeviceAdded(DeviceResponse { device })
+                }
+            }
+        };
+        self.broadcast("receiver-0", payload).await;
+    }
+
+    pub async fn remove_multizone_member(&self, device_id: &str) {
+        self.state
+            .lock()
+            .await
+            .multizone_members
+            .retain(|d| d.device_id != device_id);
+        let payload = Multizone::DeviceRemoved(DeviceRemovedResponse {
+            device_id: device_id.into(),
+        });
+        self.broadcast("receiver-0", payload).await;
+    }
+
+    /// Delays all responses to requests of the given type, e.g. `"LAUNCH"`
+    pub async fn set_delay(&self, request_type: &str, delay: Duration) {
+        let mut state = self.state.lock().await;
+        state.delays.insert(request_type.into(), delay);
+    }
+
+    /// Answers the next request of the given type with `payload` instead of the regular response
+    pub async fn respond_next_with<P: Into<Payload>>(&self, request_type: &str, payload: P) {
+        let mut state = self.state.lock().await;
+        let scripted = state.scripted.entry(request_type.into()).or_default();
+        scripted.push_back(Some(payload.into()));
+    }
+
+    /// Doesn't answer the next request of the given type at all
+    pub async fn ignore_next(&self, request_type: &str) {
+        let mut state = self.state.lock().await;
+        let scripted = state.scripted.entry(request_type.into()).or_default();
+        scripted.push_back(None);
+    }
+
+    /// Stops answering any message, including heartbeats, while keeping the connections open
+    pub async fn set_unresponsive(&self, unresponsive: bool) {
+        self.state.lock().await.unresponsive = unresponsive;
+    }
+
+    /// Closes all connections, like a receiver which got disconnected from the network
+    pub async fn drop_connections(&self) {
+        for connection in self.state.lock().await.connections.drain(..) {
+            let _ = connection.tcp_stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// All messages received so far
+    pub async fn received(&self) -> Vec<Response> {
+        self.state.lock().await.received.clone()
+    }
+
+    async fn serve(
+        state: Arc<Mutex<MockState>>,
+        tcp_stream: TcpStream,
+        tls_stream: TlsStream<TcpStream>,
+    ) {
+        let (mut read_stream, write_stream) = tls_stream.split();
+        let connection = MockConnection {
+            tcp_stream,
+            write_stream: Arc::new(Mutex::new(write_stream)),
+        };
+        state.lock().await.connections.push(connection.clone());
+
+        loop {
+            let request = match read_message(&mut read_stream).await.map(decode_message) {
+                Ok(request) => request,
+                Err(err) => {
+                    debug!("[MOCK] Connection closed: {}", err);
+                    break;
+                }
+            };
+
+            let (response, delay, broadcasts, connections) = {
+                let mut state = state.lock().await;
+                state.received.push(request.clone());
+                if state.unresponsive {
+                    continue;
+                }
+
+                let request_type = Self::request_type(&request.payload);
+                let delay = state.delays.get(&request_type).copied();
+                let scripted = state
+                    .scripted
+                    .get_mut(&request_type)
+                    .and_then(|scripted| scripted.pop_front());
+
+                let response = match scripted {
+                    Some(response) => response,
+                    None => state.handle(&request),
+                };
+                let broadcasts = std::mem::take(&mut state.pending_broadcasts);
+                let connections = state.connections.clone();
+                (response, delay, broadcasts, connections)
+            };
+
+            if response.is_none() && broadcasts.is_empty() {
+                continue;
+            }
+
+            let connection = connection.clone();
+            let respond = async move {
+                if let Some(delay) = delay {
+                    smol::Timer::after(delay).await;
+                }
+                if let Some(response) = response {
+                    connection
+                        .send(
+                            request.destination_id.clone(),
+                            request.source_id,
+                            response,
+                            request.request_id,
+                        )
+                        .await;
+                }
+
+                for payload in broadcasts {
+                    for connection in &connections {
+                        connection
+                            .send(
+                                request.destination_id.clone(),
+                                "*".into(),
+                                payload.clone(),
+                                Some(0),
+                            )
+                            .await;
+                    }
+                }
+            };
+
+            if delay.is_some() {
+                smol::spawn(respond).detach();
+            } else {
+                respond.await;
+            }
+        }
+
+        let mut state = state.lock().await;
+        state
+            .connections
+            .retain(|c| !Arc::ptr_eq(&c.write_stream, &connection.write_stream));
+    }
+
+    fn request_type(payload: &Payload) -> String {
+        serde_json::to_value(payload)
+            .ok()
+            .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+impl MockState {
+    fn handle(&mut self, request: &Response) -> Option<Payload> {
+        match &request.payload {
+            Payload::Connection(Connection::Connect | Connection::Close) => None,
+            Payload::Heartbeat(Heartbeat::Ping) => Some(Heartbeat::Pong.into()),
+            Payload::Heartbeat(Heartbeat::Pong) => None,
+            Payload::Receiver(payload) => self.handle_receiver(payload),
+            Payload::Media(payload) => self.handle_media(payload),
+            Payload::Multizone(payload) => self.handle_multizone(payload),
+            _ => None,
+        }
+    }
+
+    fn handle_receiver(&mut self, payload: &receiver::Receiver) -> Option<Payload> {
+        match payload {
+            receiver::Receiver::GetStatus => (),
+            receiver::Receiver::Launch(LaunchRequest { app_id }) => {
+                let Some(namespaces) = self.available_apps.get(app_id).cloned() else {
+                    return Some(
+                        receiver::Receiver::LaunchError(LaunchErrorResponse {
+                            reason: "NOT_FOUND".into(),
+                        })
+                        .into(),
+                    );
+                };
+
+                // Launching an app replaces the running one
+                let session_id = format!("mock-session-{}", self.next_id());
+                self.running_apps = vec![App {
+                    app_id: app_id.parse().unwrap_or_default(),
+                    app_type: "WEB".into(),
+                    display_name: format!("Mock App {}", app_id),
+                    namespaces,
+                    session_id: session_id.clone(),
+                    status_text: "Ready To Cast".into(),
+                    transport_id: session_id,
+                    universal_app_id: app_id.clone(),
+                    ..Default::default()
+                }];
+                self.media_status = None;
+                self.queue.clear();
+            }
+            receiver::Receiver::Stop(StopRequest { session_id }) => {
+                self.running_apps
+                    .retain(|app| app.session_id != *session_id);
+                self.media_status = None;
+                self.queue.clear();
+            }
+            receiver::Receiver::GetAppAvailability(AppAvailability {
+                app_id: Some(app_ids),
+                ..
+            }) => {
+                let availability = app_ids
+                    .iter()
+                    .map(|app_id| {
+                        let state = if self.available_apps.contains_key(app_id) {
+                            Availability::AppAvailable
+                        } else {
+                            Availability::AppUnavailable
+                        };
+                        (app_id.clone(), state)
+                    })
+                    .collect();
+                return Some(
+                    receiver::Receiver::GetAppAvailability(AppAvailability {
+                        app_id: None,
+                        availability: Some(availability),
+                    })
+                    .into(),
+                );
+            }
+            receiver::Receiver::SetVolume(SetVolumeRequest { volume }) => {
+                if volume.level.is_some() {
+                    self.volume.level = volume.level;
+                }
+                if volume.muted.is_some() {
+                    self.volume.muted = volume.muted;
+                }
+            }
+            _ => return None,
+        }
+
+        Some(
+            receiver::Receiver::ReceiverStatus(ReceiverStatusResponse {
+                status: Status {
+                    applications: Some(self.running_apps.clone()),
+                    is_active_input: Some(false),
+                    is_standby: Some(false),
+                    volume: self.volume.clone(),
+                },
+            })
+            .into(),
+        )
+    }
+
+    fn handle_multizone(&mut self, payload: &Multizone) -> Option<Payload> {
+        match payload {
+            Multizone::GetStatus => Some(
+                Multizone::MultizoneStatus(MultizoneStatusResponse {
+                    status: MultizoneStatus {
+                        devices: self.multizone_members.clone(),
+                        is_multichannel: Some(false),
+                    },
+                })
+                .into(),
+            ),
+            Multizone::SetVolume(SetDeviceVolumeRequest { device_id, volume }) => {
+                let device = self
+                    .multizone_members
+                    .iter_mut()
+                    .find(|d| d.device_id == *device_id)?;
+                if volume.level.is_some() {
+                    device.volume.level = volume.level;
+                }
+                if volume.muted.is_some() {
+                    device.volume.muted = volume.muted;
+                }
+
+                // The new volume only gets announced to all senders
+                let device = device.clone();
+                self.pending_broadcasts
+                    .push(Multizone::DeviceUpdated(DeviceResponse { device }).into());
+                None
+            }
+            Multizone::GetCastingGroups => Some(
+                Multizone::CastingGroups(CastingGroupsResponse {
+                    status: CastingGroups::default(),
+                })
+                .into(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn load(&mut self, data: &LoadRequestData) {
+        self.media_status = Some(MediaStatus {
+            active_track_ids: data.active_track_ids.clone(),
+            current_time: data.current_time.unwrap_or_default(),
+            media: Some(data.media.clone()),
+            media_session_id: self.next_id(),
+            playback_rate: data.playback_rate.unwrap_or(1.0),
+            player_state: if data.autoplay.unwrap_or(true) {
+                PlayerState::Playing
+            } else {
+                PlayerState::Paused
+            },
+            supported_media_commands: Command::AllBasicMedia | Command::StreamTransfer,
+            volume: Volume {
+                control_type: None,
+                muted: Some(false),
+                level: Some(1.0),
+            },
+            ..Default::default()
+        });
+        self.queue.clear();
+    }
+
+    fn handle_media(&mut self, payload: &Media) -> Option<Payload> {
+        let invalid_request = |reason| {
+            Some(
+                Media::InvalidRequest(ErrorResponseData {
+                    reason,
+                    ..Default::default()
+                })
+                .into(),
+            )
+        };
+
+        match payload {
+            Media::GetStatus(_) => (),
+            Media::Load(data) => self.load(data),
+            Media::StoreSession(data) => {
+                let Some(status) = self.media_status.as_ref() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if data.media_session_id != Some(status.media_session_id) {
+                    return invalid_request(ErrorReason::InvalidMediaSessionId);
+                }
+
+                let load_request_data = status.media.clone().map(|media| LoadRequestData {
+                    active_track_ids: status.active_track_ids.clone(),
+                    autoplay: Some(matches!(status.player_state, PlayerState::Playing)),
+                    current_time: Some(status.current_time),
+                    media,
+                    playback_rate: Some(status.playback_rate),
+                    ..Default::default()
+                });
+                return Some(
+                    Media::SessionState(StoreSessionResponseData {
+                        session_state: SessionState { load_request_data },
+                    })
+                    .into(),
+                );
+            }
+            Media::ResumeSession(data) => match &data.session_state.load_request_data {
+                Some(load_request_data) => self.load(load_request_data),
+                None => return invalid_request(ErrorReason::InvalidParams),
+            },
+            Media::QueueLoad(data) => {
+                self.queue = self.new_queue_items(&data.items);
+                self.media_status = Some(MediaStatus {
+                    current_time: data.current_time.unwrap_or_default(),
+                    media_session_id: self.next_id(),
+                    playback_rate: 1.0,
+                    player_state: PlayerState::Playing,
+                    repeat_mode: data.repeat_mode.clone(),
+                    supported_media_commands: Command::AllBasicMedia,
+                    volume: Volume {
+                        control_type: None,
+                        muted: Some(false),
+                        level: Some(1.0),
+                    },
+                    ..Default::default()
+                });
+                self.jump_to_index(data.start_index.unwrap_or_default());
+            }
+            Media::QueueInsert(data) => {
+                if self.media_status.is_none() {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                }
+                let items = self.new_queue_items(&data.items);
+                let item_ids = items.iter().filter_map(|item| item.item_id).collect();
+
+                let position = self.queue_position(data.insert_before);
+                self.queue.splice(position..position, items);
+                self.pending_broadcasts.push(
+                    Media::QueueChange(QueueChange {
+                        change_type: Some(QueueChangeType::Insert),
+                        insert_before: data.insert_before,
+                        item_ids: Some(item_ids),
+                        ..Default::default()
+                    })
+                    .into(),
+                );
+            }
+            Media::QueueRemove(data) => {
+                let Some(status) = self.media_status.as_ref() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                let current_index = self
+                    .queue
+                    .iter()
+                    .position(|item| item.item_id == status.current_item_id);
+
+                self.queue
+                    .retain(|item| !data.item_ids.contains(&item.item_id.unwrap_or_default()));
+                // Continue with the following item if the current one got removed
+                if let Some(index) = current_index {
+                    if self.queue_index(status.current_item_id).is_none() {
+                        self.jump_to_index(index as i32);
+                    }
+                }
+                self.pending_broadcasts.push(
+                    Media::QueueChange(QueueChange {
+                        change_type: Some(QueueChangeType::Remove),
+                        item_ids: Some(data.item_ids.clone()),
+                        ..Default::default()
+                    })
+                    .into(),
+                );
+            }
+            Media::QueueReorder(data) => {
+                if self.media_status.is_none() {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                }
+                let (moved, mut queue): (Vec<_>, Vec<_>) = self
+                    .queue
+                    .drain(..)
+                    .partition(|item| data.item_ids.contains(&item.item_id.unwrap_or_default()));
+                let position = data
+                    .insert_before
+                    .and_then(|id| queue.iter().position(|item| item.item_id == Some(id)))
+                    .unwrap_or(queue.len());
+                queue.splice(position..position, moved);
+                self.queue = queue;
+
+                self.pending_broadcasts.push(
+                    Media::QueueChange(QueueChange {
+                        change_type: Some(QueueChangeType::Update),
+                        insert_before: data.insert_before,
+                        reorder_item_ids: Some(data.item_ids.clone()),
+                        ..Default::default()
+                    })
+                    .into(),
+                );
+            }
+            Media::QueueUpdate(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if let Some(repeat_mode) = &data.repeat_mode {
+                    status.repeat_mode = Some(repeat_mode.clone());
+                }
+
+                if let Some(items) = &data.items {
+                    for item in items {
+                        if let Some(existing) = self
+                            .queue
+                            .iter_mut()
+                            .find(|existing| existing.item_id == item.item_id)
+                        {
+                            *existing = item.clone();
+                        }
+                    }
+                    self.pending_broadcasts.push(
+                        Media::QueueChange(QueueChange {
+                            change_type: Some(QueueChangeType::ItemsChange),
+                            item_ids: Some(items.iter().filter_map(|item| item.item_id).collect()),
+                            ..Default::default()
+                        })
+                        .into(),
+                    );
+                }
+
+                if let Some(item_id) = data.current_item_id {
+                    if let Some(index) = self.queue_index(Some(item_id)) {
+                        self.jump_to_index(index as i32);
+                    }
+                }
+                if let Some(jump) = data.jump {
+                    self.jump(jump);
+                }
+            }
+            Media::QueueNext(_) | Media::QueuePrev(_) => {
+                if self.media_status.is_none() {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                }
+                self.jump(if matches!(payload, Media::QueueNext(_)) {
+                    1
+                } else {
+                    -1
+                });
+            }
+            Media::QueueGetItemIds(_) => {
+                return Some(
+                    Media::QueueItemIds(QueueIds {
+                        item_ids: Some(self.queue.iter().filter_map(|item| item.item_id).collect()),
+                    })
+                    .into(),
+                );
+            }
+            Media::QueueGetItems(data) => {
+                let items = self
+                    .queue
+                    .iter()
+                    .filter(|item| data.item_ids.contains(&item.item_id.unwrap_or_default()))
+                    .cloned()
+                    .collect();
+                return Some(Media::QueueItems(ItemsInfo { items: Some(items) }).into());
+            }
+            Media::Play(data) | Media::Pause(data) | Media::Stop(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if data.media_session_id != Some(status.media_session_id) {
+                    return invalid_request(ErrorReason::InvalidMediaSessionId);
+                }
+
+                match payload {
+                    Media::Play(_) => status.player_state = PlayerState::Playing,
+                    Media::Pause(_) => status.player_state = PlayerState::Paused,
+                    _ => {
+                        status.player_state = PlayerState::Idle;
+                        status.idle_reason = Some(IdleReason::Cancelled);
+                        let status = self.media_status.take();
+                        return Some(
+                            Media::MediaStatus(ResponseData {
+                                status: status.into_iter().collect(),
+                            })
+                            .into(),
+                        );
+                    }
+                }
+            }
+            Media::Seek(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if let Some(current_time) = data.current_time {
+                    status.current_time = current_time;
+                }
+                if let Some(relative_time) = data.relative_time {
+                    status.current_time += relative_time;
+                }
+                match data.resume_state {
+                    Some(SeekResumeState::PlaybackStart) => {
+                        status.player_state = PlayerState::Playing
+                    }
+                    Some(SeekResumeState::PlaybackPause) => {
+                        status.player_state = PlayerState::Paused
+                    }
+                    None => (),
+                }
+            }
+            Media::SetPlaybackRate(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if let Some(playback_rate) = data.playback_rate {
+                    status.playback_rate = playback_rate;
+                }
+                if let Some(relative_playback_rate) = data.relative_playback_rate {
+                    status.playback_rate *= relative_playback_rate;
+                }
+            }
+            Media::EditTracksInfo(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if data.active_track_ids.is_some() {
+                    status.active_track_ids = data.active_track_ids.clone();
+                }
+                if data.enable_text_tracks == Some(false) {
+                    status.active_track_ids = Some(vec![]);
+                }
+            }
+            Media::EditAudioTracks(_) => {
+                if self.media_status.is_none() {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                }
+            }
+            Media::SetVolume(data) => {
+                let Some(status) = self.media_status.as_mut() else {
+                    return Some(Media::InvalidPlayerState(ErrorResponseData::default()).into());
+                };
+                if data.volume.level.is_some() {
+                    status.volume.level = data.volume.level;
+                }
+                if data.volume.muted.is_some() {
+                    status.volume.muted = data.volume.muted;
+                }
+            }
+            _ => return invalid_request(ErrorReason::InvalidCommand),
+        }
+
+        Some(
+            Media::MediaStatus(ResponseData {
+                status: self.media_status.clone().into_iter().collect(),
+            })
+            .into(),
+        )
+    }
+
+    fn new_queue_items(&mut self, items: &[QueueItem]) -> Vec<QueueItem> {
+        items
+            .iter()
+            .map(|item| QueueItem {
+                item_id: Some(self.next_id()),
+                ..item.clone()
+            })
+            .collect()
+    }
+
+    fn queue_index(&self, item_id: Option<i32>) -> Option<usize> {
+        self.queue.iter().position(|item| item.item_id == item_id)
+    }
+
+    // Position in the queue before the given item, or the end
+    fn queue_position(&self, insert_before: Option<i32>) -> usize {
+        insert_before
+            .and_then(|id| self.queue_index(Some(id)))
+            .unwrap_or(self.queue.len())
+    }
+
+    fn jump(&mut self, offset: i32) {
+        let Some(status) = self.media_status.as_ref() else {
+            return;
+        };
+        let index = self.queue_index(status.current_item_id).unwrap_or_default() as i32;
+        let mut index = index + offset;
+        if matches!(status.repeat_mode, Some(RepeatMode::RepeatAll)) {
+            index = index.rem_euclid(self.queue.len().max(1) as i32);
+        }
+        self.jump_to_index(index);
+    }
+
+    fn jump_to_index(&mut self, index: i32) {
+        let Some(status) = self.media_status.as_mut() else {
+            return;
+        };
+        let item = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.queue.get(index))
+            .or(self.queue.last());
+
+        status.current_item_id = item.and_then(|item| item.item_id);
+        status.media = item.and_then(|item| item.media.clone());
+        status.current_time = item.and_then(|item| item.start_time).unwrap_or_default();
+    }
+
+    fn next_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+impl Drop for MockReceiver {
+    fn drop(&mut self) {
+        if let Some(mut state) = self.state.try_lock() {
+            for connection in state.connections.drain(..) {
+                let _ = connection.tcp_stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
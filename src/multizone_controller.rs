@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use async_broadcast::InactiveReceiver;
+use futures_util::{Stream, StreamExt};
+use smol::lock::Mutex;
+use smol_timeout::TimeoutExt;
+
+use crate::namespace::multizone::*;
+use crate::namespace::NamespaceUrn;
+use crate::receiver::{event_channel, spawn_event_sync};
+use crate::{Error, Payload, Receiver};
+
+#[derive(Debug)]
+struct Members {
+    devices: Mutex<Vec<Device>>,
+    events: async_broadcast::Sender<Vec<Device>>,
+    _events_rx: InactiveReceiver<Vec<Device>>,
+}
+
+impl Members {
+    fn new() -> Self {
+        let (events, events_rx) = event_channel();
+
+        Self {
+            devices: Mutex::default(),
+            events,
+            _events_rx: events_rx,
+        }
+    }
+
+    async fn process(&self, multizone: &Multizone) {
+        let mut devices = self.devices.lock().await;
+        match multizone {
+            Multizone::MultizoneStatus(response) => {
+                *devices = response.status.devices.clone();
+            }
+            Multizone::DeviceAdded(DeviceResponse { device })
+            | Multizone::DeviceUpdated(DeviceResponse { device }) => {
+                match devices.iter_mut().find(|d| d.device_id == device.device_id) {
+                    // Updates can be processed twice, see `set_member_volume()`
+                    Some(existing) if existing == device => return,
+                    Some(existing) => *existing = device.clone(),
+                    None => devices.push(device.clone()),
+                }
+            }
+            Multizone::DeviceRemoved(DeviceRemovedResponse { device_id }) => {
+                devices.retain(|d| d.device_id != *device_id);
+            }
+            _ => return,
+        }
+
+        let _ = self.events.try_broadcast(devices.clone());
+    }
+}
+
+/// Keeps track of the members of a cast group, and their volume and capabilities.
+/// Only group receivers, e.g. speaker groups, support the multizone namespace.
+#[derive(Clone, Debug)]
+pub struct MultizoneController {
+    receiver: Receiver,
+    members: Arc<Members>,
+}
+
+impl MultizoneController {
+    pub fn new(receiver: Receiver) -> Self {
+        let controller = Self {
+            receiver,
+            members: Arc::new(Members::new()),
+        };
+        let events = controller
+            .receiver
+            .namespace_events(NamespaceUrn::Multizone);
+        spawn_event_sync(
+            events,
+            Arc::downgrade(&controller.members),
+            |members, response| async move {
+                if let Payload::Multizone(multizone) = &response.payload {
+                    members.process(multizone).await;
+                }
+            },
+        );
+        controller
+    }
+
+    /// The latest known members of the group, see [`MultizoneController::refresh`]
+    pub async fn members(&self) -> Vec<Device> {
+        self.members.devices.lock().await.clone()
+    }
+
+    pub async fn member(&self, device_id: &str) -> Option<Device> {
+        self.members
+            .devices
+            .lock()
+            .await
+            .iter()
+            .find(|device| device.device_id == device_id)
+            .cloned()
+    }
+
+    /// The complete member list, whenever a member got added, updated or removed
+    pub fn watch_members(&self) -> impl Stream<Item = Vec<Device>> + Unpin + Send {
+        self.members.events.new_receiver()
+    }
+
+    /// Fetches the members of the group
+    pub async fn refresh(&self) -> Result<Vec<Device>, Error> {
+        let response = self
+            .receiver
+            .send_request(self.receiver.platform(), Multizone::GetStatus)
+            .await?;
+
+        if let Payload::Multizone(multizone @ Multizone::MultizoneStatus(_)) = &response.payload {
+            self.members.process(multizone).await;
+            return Ok(self.members().await);
+        }
+
+        Err(Error::unexpected_response(response))
+    }
+
+    /// Changes the volume of a single member, unlike [`Receiver::set_volume`]
+    /// which changes the volume of the whole group. Returns the updated member.
+    pub async fn set_member_volume(
+        &self,
+        device_id: &str,
+        level: f64,
+        muted: bool,
+    ) -> Result<Device, Error> {
+        // The receiver doesn't respond, but announces the new volume to all senders
+        let mut updates = self.receiver.namespace_events(NamespaceUrn::Multizone);
+        let request = Multizone::set_volume_request(device_id.into(), level, muted);
+        self.receiver
+            .send(self.receiver.platform(), request)
+            .await?;
+
+        let updated = async {
+            while let Some(response) = updates.next().await {
+                if let Payload::Multizone(multizone @ Multizone::DeviceUpdated(update)) =
+                    &response.payload
+                {
+                    if update.device.device_id == device_id {
+                        self.members.process(multizone).await;
+                        return Some(update.device.clone());
+                    }
+                }
+            }
+            None
+        };
+
+        updated
+            .timeout(self.receiver.config().request_timeout)
+            .await
+            .flatten()
+            .ok_or_else(|| {
+                Error::response_timeout(
+                    NamespaceUrn::Multizone,
+                    None,
+                    &self.receiver.platform().transport_id,
+                )
+            })
+    }
+
+    /// Groups the device is a member of, which are currently casting
+    pub async fn casting_groups(&self) -> Result<Vec<CastingGroup>, Error> {
+        let response = self
+            .receiver
+            .send_request(self.receiver.platform(), Multizone::GetCastingGroups)
+            .await?;
+
+        if let Payload::Multizone(Multizone::CastingGroups(response)) = response.payload {
+            return Ok(response.status.groups);
+        }
+
+        Err(Error::unexpected_response(response))
+    }
+}
@@ -0,0 +1,13 @@
+// Unlike all other namespaces, device auth messages are protobuf encoded. The error message
+// is renamed, as `crate::AuthError` is the error of `Receiver::verify_device`.
+pub use crate::proto::{
+    auth_error::ErrorType, AuthChallenge, AuthError as AuthErrorMessage, AuthResponse,
+    DeviceAuthMessage, HashAlgorithm, SignatureAlgorithm,
+};
+use crate::Payload;
+
+impl From<DeviceAuthMessage> for Payload {
+    fn from(val: DeviceAuthMessage) -> Self {
+        Payload::DeviceAuth(val)
+    }
+}
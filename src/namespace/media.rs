@@ -44,15 +44,16 @@ pub enum Media {
     // Response
     MediaStatus(ResponseData<MediaStatus>),
     CloudStatus(ResponseData<CloudMediaStatus>),
-    QueueChange(ResponseData<QueueChange>),
-    QueueItems(ResponseData<ItemsInfo>),
-    QueueItemIds(ResponseData<QueueIds>),
+    QueueChange(QueueChange),
+    QueueItems(ItemsInfo),
+    QueueItemIds(QueueIds),
     SessionState(StoreSessionResponseData),
 
     InvalidRequest(ErrorResponseData),
-    InvalidPlayerState,
-    LoadFailed,
-    LoadCancelled,
+    InvalidPlayerState(ErrorResponseData),
+    LoadFailed(ErrorResponseData),
+    LoadCancelled(ErrorResponseData),
+    Error(ErrorResponseData),
 }
 
 impl From<Media> for Payload {
@@ -196,7 +197,7 @@ pub struct QueueInsertRequestData {
     pub current_item_id: Option<i32>,
     pub current_item_index: Option<i32>,
     pub current_time: Option<f64>,
-    pub insert_before: Option<bool>,
+    pub insert_before: Option<i32>,
     pub items: Vec<QueueItem>,
     pub media_session_id: Option<i32>,
 }
@@ -231,7 +232,7 @@ pub struct QueueRemoveRequestData {
 pub struct QueueReorderRequestData {
     pub current_item_id: Option<i32>,
     pub current_time: Option<f64>,
-    pub insert_before: Option<bool>,
+    pub insert_before: Option<i32>,
     pub item_ids: Vec<i32>,
     pub media_session_id: Option<i32>,
 }
@@ -280,6 +281,7 @@ pub struct SeekRequestData {
     pub current_time: Option<f64>,
     pub media_session_id: Option<i32>,
     pub relative_time: Option<f64>,
+    pub resume_state: Option<SeekResumeState>,
 }
 
 #[skip_serializing_none]
@@ -298,15 +300,17 @@ pub struct SetCredentialsRequestData {
 #[builder(setter(strip_option, into), default)]
 pub struct SetPlaybackRateRequestData {
     pub media_session_id: Option<i32>,
-    pub playback_rate: Option<u32>,
-    pub relative_playback_rate: Option<i32>,
+    pub playback_rate: Option<f64>,
+    pub relative_playback_rate: Option<f64>,
 }
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug, Default, Builder)]
 #[serde(rename_all = "camelCase")]
 #[builder(setter(strip_option, into), default)]
-pub struct StoreSessionRequestData {}
+pub struct StoreSessionRequestData {
+    pub media_session_id: Option<i32>,
+}
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug, Default, Builder)]
@@ -336,16 +340,20 @@ pub struct ResponseData<T> {
     pub status: Vec<T>,
 }
 
-impl<T: Clone + Default> ResponseData<T> {
-    pub fn first(&self) -> T {
-        self.status.first().cloned().unwrap()
+impl<T> ResponseData<T> {
+    /// The first status, there is none if e.g. the media session got stopped
+    pub fn first(&self) -> Option<&T> {
+        self.status.first()
     }
 }
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct ErrorResponseData {
+    pub custom_data: Option<serde_json::Value>,
+    pub detailed_error_code: Option<DetailedErrorCode>,
+    pub item_id: Option<i32>,
     pub reason: ErrorReason,
 }
 
@@ -538,6 +546,30 @@ pub struct MediaInformation {
     pub vmap_ads_request: Option<VastAdsRequest>,
 }
 
+impl MediaInformation {
+    /// Guesses the category from the content type, or from the metadata
+    /// for content types like HLS or DASH playlists which don't tell.
+    pub fn category(&self) -> Option<MediaCategory> {
+        let content_type = self.content_type.to_ascii_lowercase();
+        if content_type.starts_with("video/") || self.hls_video_segment_format.is_some() {
+            return Some(MediaCategory::Video);
+        } else if content_type.starts_with("audio/") {
+            return Some(MediaCategory::Audio);
+        } else if content_type.starts_with("image/") {
+            return Some(MediaCategory::Image);
+        }
+
+        match &self.metadata.as_ref()?.metadata_type {
+            MetadataType::Movie(_) | MetadataType::TvShow(_) => Some(MediaCategory::Video),
+            MetadataType::MusicTrack(_) | MetadataType::AudiobookChapter(_) => {
+                Some(MediaCategory::Audio)
+            }
+            MetadataType::Photo(_) => Some(MediaCategory::Image),
+            MetadataType::Generic(_) => None,
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug, Default, Builder)]
 #[serde(rename_all = "camelCase")]
@@ -569,7 +601,7 @@ pub struct MediaStatus {
     pub loading_item_id: Option<i32>,
     pub media: Option<MediaInformation>,
     pub media_session_id: i32,
-    pub playback_rate: i32,
+    pub playback_rate: f64,
     pub player_state: PlayerState,
     pub preloaded_item_id: Option<i32>,
     pub queue_data: Option<QueueData>,
@@ -665,6 +697,7 @@ pub struct QueueChange {
     pub insert_before: Option<i32>,
     pub item_ids: Option<Vec<i32>>,
     pub reorder_item_ids: Option<Vec<i32>>,
+    pub sequence_number: Option<i32>,
 }
 
 #[skip_serializing_none]
@@ -904,6 +937,46 @@ impl<'de> serde::Deserialize<'de> for Command {
     }
 }
 
+bitflags::bitflags! {
+    /// Hardware capabilities of a receiver, e.g. the `ca` TXT record of discovered devices
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+    pub struct DeviceCapabilities: u32 {
+        const VideoOut = 1;
+        const VideoIn = 2;
+        const AudioOut = 4;
+        const AudioIn = 8;
+        const DevMode = 16;
+        const MultizoneGroup = 32;
+    }
+}
+
+impl DeviceCapabilities {
+    /// Whether the device can play audio, but not video, e.g. speakers and speaker groups
+    pub fn is_audio_only(&self) -> bool {
+        self.contains(Self::AudioOut) && !self.contains(Self::VideoOut)
+    }
+}
+
+impl serde::Serialize for DeviceCapabilities {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u32(self.bits())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DeviceCapabilities {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Newer devices announce additional capabilities, which shouldn't get lost
+        let bits = u32::deserialize(deserializer)?;
+        Ok(DeviceCapabilities::from_bits_retain(bits))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ContainerType {
@@ -919,6 +992,167 @@ pub enum ContentFilteringMode {
     FilterExplicit,
 }
 
+/// Error codes of the Web Receiver, sent as numbers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DetailedErrorCode {
+    MediaUnknown,
+    MediaAborted,
+    MediaDecode,
+    MediaNetwork,
+    MediaSrcNotSupported,
+    SourceBufferFailure,
+    MediakeysUnknown,
+    MediakeysNetwork,
+    MediakeysUnsupported,
+    MediakeysWebcrypto,
+    NetworkUnknown,
+    SegmentNetwork,
+    HlsNetworkMasterPlaylist,
+    HlsNetworkPlaylist,
+    HlsNetworkNoKeyResponse,
+    HlsNetworkKeyLoad,
+    HlsNetworkInvalidSegment,
+    HlsSegmentParsing,
+    DashNetwork,
+    DashNoInit,
+    SmoothNetwork,
+    SmoothNoMediaData,
+    ManifestUnknown,
+    HlsManifestMaster,
+    HlsManifestPlaylist,
+    DashManifestUnknown,
+    DashManifestNoPeriods,
+    DashManifestNoMimetype,
+    DashInvalidSegmentInfo,
+    SmoothManifest,
+    SegmentUnknown,
+    TextUnknown,
+    App,
+    BreakClipLoadingError,
+    BreakSeekInterceptorError,
+    ImageError,
+    LoadInterrupted,
+    LoadFailed,
+    MediaErrorMessage,
+    #[default]
+    Generic,
+    /// A code which isn't known yet, kept to be able to send it back unchanged
+    Other(i32),
+}
+
+impl DetailedErrorCode {
+    fn from_code(code: i32) -> Self {
+        match code {
+            100 => Self::MediaUnknown,
+            101 => Self::MediaAborted,
+            102 => Self::MediaDecode,
+            103 => Self::MediaNetwork,
+            104 => Self::MediaSrcNotSupported,
+            110 => Self::SourceBufferFailure,
+            200 => Self::MediakeysUnknown,
+            201 => Self::MediakeysNetwork,
+            202 => Self::MediakeysUnsupported,
+            203 => Self::MediakeysWebcrypto,
+            300 => Self::NetworkUnknown,
+            301 => Self::SegmentNetwork,
+            311 => Self::HlsNetworkMasterPlaylist,
+            312 => Self::HlsNetworkPlaylist,
+            313 => Self::HlsNetworkNoKeyResponse,
+            314 => Self::HlsNetworkKeyLoad,
+            315 => Self::HlsNetworkInvalidSegment,
+            316 => Self::HlsSegmentParsing,
+            321 => Self::DashNetwork,
+            322 => Self::DashNoInit,
+            331 => Self::SmoothNetwork,
+            332 => Self::SmoothNoMediaData,
+            400 => Self::ManifestUnknown,
+            411 => Self::HlsManifestMaster,
+            412 => Self::HlsManifestPlaylist,
+            420 => Self::DashManifestUnknown,
+            421 => Self::DashManifestNoPeriods,
+            422 => Self::DashManifestNoMimetype,
+            423 => Self::DashInvalidSegmentInfo,
+            431 => Self::SmoothManifest,
+            500 => Self::SegmentUnknown,
+            600 => Self::TextUnknown,
+            900 => Self::App,
+            901 => Self::BreakClipLoadingError,
+            902 => Self::BreakSeekInterceptorError,
+            903 => Self::ImageError,
+            904 => Self::LoadInterrupted,
+            905 => Self::LoadFailed,
+            906 => Self::MediaErrorMessage,
+            999 => Self::Generic,
+            _ => Self::Other(code),
+        }
+    }
+
+    /// The number the receiver sends for the code
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::MediaUnknown => 100,
+            Self::MediaAborted => 101,
+            Self::MediaDecode => 102,
+            Self::MediaNetwork => 103,
+            Self::MediaSrcNotSupported => 104,
+            Self::SourceBufferFailure => 110,
+            Self::MediakeysUnknown => 200,
+            Self::MediakeysNetwork => 201,
+            Self::MediakeysUnsupported => 202,
+            Self::MediakeysWebcrypto => 203,
+            Self::NetworkUnknown => 300,
+            Self::SegmentNetwork => 301,
+            Self::HlsNetworkMasterPlaylist => 311,
+            Self::HlsNetworkPlaylist => 312,
+            Self::HlsNetworkNoKeyResponse => 313,
+            Self::HlsNetworkKeyLoad => 314,
+            Self::HlsNetworkInvalidSegment => 315,
+            Self::HlsSegmentParsing => 316,
+            Self::DashNetwork => 321,
+            Self::DashNoInit => 322,
+            Self::SmoothNetwork => 331,
+            Self::SmoothNoMediaData => 332,
+            Self::ManifestUnknown => 400,
+            Self::HlsManifestMaster => 411,
+            Self::HlsManifestPlaylist => 412,
+            Self::DashManifestUnknown => 420,
+            Self::DashManifestNoPeriods => 421,
+            Self::DashManifestNoMimetype => 422,
+            Self::DashInvalidSegmentInfo => 423,
+            Self::SmoothManifest => 431,
+            Self::SegmentUnknown => 500,
+            Self::TextUnknown => 600,
+            Self::App => 900,
+            Self::BreakClipLoadingError => 901,
+            Self::BreakSeekInterceptorError => 902,
+            Self::ImageError => 903,
+            Self::LoadInterrupted => 904,
+            Self::LoadFailed => 905,
+            Self::MediaErrorMessage => 906,
+            Self::Generic => 999,
+            Self::Other(code) => *code,
+        }
+    }
+}
+
+impl serde::Serialize for DetailedErrorCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DetailedErrorCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self::from_code(i32::deserialize(deserializer)?))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ErrorReason {
@@ -1009,7 +1243,7 @@ pub enum IdleReason {
     Error,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MediaCategory {
     #[default]
@@ -1201,3 +1435,80 @@ pub enum UserActionContext {
     Player,
     Coach,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media(content_type: &str, metadata_type: Option<MetadataType>) -> MediaInformation {
+        MediaInformation {
+            content_type: content_type.to_string(),
+            metadata: metadata_type.map(|metadata_type| MediaMetadata {
+                metadata_type,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn categorizes_by_content_type() {
+        let movie = Some(MetadataType::Movie(MovieMediaMetadata::default()));
+        assert_eq!(
+            media("audio/mpeg", None).category(),
+            Some(MediaCategory::Audio)
+        );
+        assert_eq!(
+            media("Video/MP4", None).category(),
+            Some(MediaCategory::Video)
+        );
+        assert_eq!(
+            media("image/jpeg", None).category(),
+            Some(MediaCategory::Image)
+        );
+        assert_eq!(
+            media("audio/mpeg", movie).category(),
+            Some(MediaCategory::Audio)
+        );
+    }
+
+    #[test]
+    fn categorizes_hls_video_segments_as_video() {
+        let mut media = media("application/x-mpegurl", None);
+        assert_eq!(media.category(), None);
+        media.hls_video_segment_format = Some(HlsVideoSegmentFormat::Fmp4);
+        assert_eq!(media.category(), Some(MediaCategory::Video));
+    }
+
+    #[test]
+    fn falls_back_to_metadata() {
+        let cases = [
+            (
+                MetadataType::Movie(Default::default()),
+                Some(MediaCategory::Video),
+            ),
+            (
+                MetadataType::TvShow(Default::default()),
+                Some(MediaCategory::Video),
+            ),
+            (
+                MetadataType::MusicTrack(Default::default()),
+                Some(MediaCategory::Audio),
+            ),
+            (
+                MetadataType::AudiobookChapter(Default::default()),
+                Some(MediaCategory::Audio),
+            ),
+            (
+                MetadataType::Photo(Default::default()),
+                Some(MediaCategory::Image),
+            ),
+            (MetadataType::Generic(Default::default()), None),
+        ];
+        for (metadata_type, category) in cases {
+            let media = media("application/x-mpegurl", Some(metadata_type));
+            assert_eq!(media.category(), category);
+        }
+        assert_eq!(media("", None).category(), None);
+    }
+}
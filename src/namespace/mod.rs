@@ -7,6 +7,7 @@ use strum_macros::{Display, EnumString};
 use crate::Payload;
 
 pub mod connection;
+pub mod device_auth;
 pub mod heartbeat;
 pub mod media;
 pub mod multizone;
@@ -39,9 +40,8 @@ pub enum NamespaceUrn {
     Sse,
 }
 
+// The message type is part of the fields, custom namespaces don't necessarily have one
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-#[serde(tag = "type")]
 pub struct Custom {
     #[serde(skip)]
     pub namespace: NamespaceUrn,
@@ -55,6 +55,19 @@ impl From<Custom> for Payload {
     }
 }
 
+/// Binary payload of a namespace which isn't known to be protobuf encoded
+#[derive(Clone, Debug, Default)]
+pub struct CustomBinary {
+    pub namespace: NamespaceUrn,
+    pub data: Vec<u8>,
+}
+
+impl From<CustomBinary> for Payload {
+    fn from(val: CustomBinary) -> Self {
+        Payload::CustomBinary(val)
+    }
+}
+
 impl<'de> Deserialize<'de> for NamespaceUrn {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
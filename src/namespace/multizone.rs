@@ -1,3 +1,4 @@
+use crate::namespace::media::DeviceCapabilities;
 use crate::Payload;
 
 use crate::Volume;
@@ -6,7 +7,31 @@ use crate::Volume;
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[serde(tag = "type")]
 pub enum Multizone {
+    // Request
+    GetStatus,
+    GetCastingGroups,
+    SetVolume(SetDeviceVolumeRequest),
+
+    // Response
+    MultizoneStatus(MultizoneStatusResponse),
+    CastingGroups(CastingGroupsResponse),
+    DeviceAdded(DeviceResponse),
     DeviceUpdated(DeviceResponse),
+    DeviceRemoved(DeviceRemovedResponse),
+    PlaybackSessionUpdated(PlaybackSessionResponse),
+}
+
+impl Multizone {
+    pub fn set_volume_request(device_id: String, level: f64, muted: bool) -> Self {
+        Self::SetVolume(SetDeviceVolumeRequest {
+            device_id,
+            volume: Volume {
+                control_type: None,
+                muted: Some(muted),
+                level: Some(level),
+            },
+        })
+    }
 }
 
 impl From<Multizone> for Payload {
@@ -15,6 +40,49 @@ impl From<Multizone> for Payload {
     }
 }
 
+/// Changes the volume of a single group member
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDeviceVolumeRequest {
+    pub device_id: String,
+    pub volume: Volume,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MultizoneStatusResponse {
+    pub status: MultizoneStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MultizoneStatus {
+    /// Members of the group
+    pub devices: Vec<Device>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_multichannel: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CastingGroupsResponse {
+    pub status: CastingGroups,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CastingGroups {
+    pub groups: Vec<CastingGroup>,
+}
+
+/// A group the device is a member of, which is currently casting
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CastingGroup {
+    pub device_id: String,
+    pub name: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceResponse {
@@ -23,8 +91,23 @@ pub struct DeviceResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
+pub struct DeviceRemovedResponse {
+    pub device_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackSessionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playback_session: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct Device {
-    pub capabilities: u32,
+    pub capabilities: DeviceCapabilities,
     pub device_id: String,
     pub name: String,
     pub volume: Volume,
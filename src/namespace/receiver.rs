@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::{app::AppId, App, Payload, Volume};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -9,7 +11,10 @@ pub enum Receiver {
     Launch(LaunchRequest),
     SetVolume(SetVolumeRequest),
     Stop(StopRequest),
+    // Also the response, whose type is sent as `responseType`
+    GetAppAvailability(AppAvailability),
     LaunchError(LaunchErrorResponse),
+    InvalidRequest(InvalidRequestResponse),
 
     // Response
     ReceiverStatus(ReceiverStatusResponse),
@@ -35,6 +40,13 @@ impl Receiver {
     pub fn stop_request(session_id: String) -> Self {
         Self::Stop(StopRequest { session_id })
     }
+
+    pub fn app_availability_request(app_ids: &[AppId]) -> Self {
+        Self::GetAppAvailability(AppAvailability {
+            app_id: Some(app_ids.iter().map(AppId::to_string).collect()),
+            availability: None,
+        })
+    }
 }
 
 impl From<Receiver> for Payload {
@@ -61,6 +73,24 @@ pub struct SetVolumeRequest {
     pub volume: Volume,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AppAvailability {
+    /// Apps to check, only set in the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<Vec<String>>,
+    /// Availability by app id, only set in the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability: Option<HashMap<String, Availability>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Availability {
+    AppAvailable,
+    AppUnavailable,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ReceiverStatusResponse {
@@ -73,6 +103,12 @@ pub struct LaunchErrorResponse {
     pub reason: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidRequestResponse {
+    pub reason: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Status {
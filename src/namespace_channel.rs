@@ -0,0 +1,143 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use futures_util::{future, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::namespace::{Custom, NamespaceUrn};
+use crate::{App, Error, Payload, Receiver};
+
+/// Typed messages of a custom namespace, e.g. for the own protocol of a custom receiver app.
+/// The messages have to serialize to JSON objects.
+pub struct NamespaceChannel<T> {
+    app: App,
+    receiver: Receiver,
+    namespace: NamespaceUrn,
+
+    // Overrides the default request timeout of the receiver
+    timeout: Option<Duration>,
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> NamespaceChannel<T> {
+    /// Fails with [`Error::UnsupportedNamespace`] unless `namespace` is a custom one the app supports
+    pub fn new(app: App, receiver: Receiver, namespace: NamespaceUrn) -> Result<Self, Error> {
+        // Built-in namespaces have typed payloads and controllers of their own
+        if !matches!(namespace, NamespaceUrn::Custom(_)) || !app.namespaces.contains(&namespace) {
+            return Err(Error::UnsupportedNamespace);
+        }
+
+        Ok(Self {
+            app,
+            receiver,
+            namespace,
+            timeout: None,
+            _message: PhantomData,
+        })
+    }
+
+    /// Returns a channel whose requests wait up to `timeout` for a response
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn namespace(&self) -> &NamespaceUrn {
+        &self.namespace
+    }
+
+    pub async fn send(&self, message: T) -> Result<(), Error> {
+        let payload = self.payload(message)?;
+        self.receiver.send(&self.app, payload).await
+    }
+
+    /// Sends the message with a request id, and returns the message the app responded with
+    pub async fn request(&self, message: T) -> Result<T, Error> {
+        let payload = self.payload(message)?;
+        let response = match self.timeout {
+            Some(timeout) => {
+                self.receiver
+                    .send_request_with_timeout(&self.app, payload, timeout)
+                    .await
+            }
+            None => self.receiver.send_request(&self.app, payload).await,
+        }?;
+
+        match response.payload {
+            Payload::Custom(custom) => Ok(Self::message(custom)?),
+            _ => Err(Error::unexpected_response(response)),
+        }
+    }
+
+    /// All messages the app sent in this namespace, including responses to requests.
+    /// Messages which can't be deserialized are skipped.
+    pub fn messages(&self) -> impl Stream<Item = T> + Unpin + Send
+    where
+        T: Send,
+    {
+        let namespace = self.namespace.clone();
+        self.receiver
+            .transport_events(self.app.transport_id.clone())
+            .filter_map(move |response| {
+                let message = match response.payload {
+                    Payload::Custom(custom) if response.namespace == namespace => {
+                        Self::message(custom)
+                            .map_err(|err| warn!("Unable to deserialize {}: {}", namespace, err))
+                            .ok()
+                    }
+                    _ => None,
+                };
+                future::ready(message)
+            })
+    }
+
+    fn payload(&self, message: T) -> Result<Payload, Error> {
+        let Value::Object(fields) = serde_json::to_value(message)? else {
+            return Err(Error::Protocol(format!(
+                "Messages of {} need to serialize to a JSON object",
+                self.namespace
+            )));
+        };
+
+        Ok(Custom {
+            namespace: self.namespace.clone(),
+            fields: fields.into_iter().collect(),
+        }
+        .into())
+    }
+
+    fn message(custom: Custom) -> Result<T, serde_json::Error> {
+        serde_json::from_value(Value::Object(custom.fields.into_iter().collect()))
+    }
+}
+
+impl<T> Clone for NamespaceChannel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            app: self.app.clone(),
+            receiver: self.receiver.clone(),
+            namespace: self.namespace.clone(),
+            timeout: self.timeout,
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for NamespaceChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamespaceChannel")
+            .field("app", &self.app)
+            .field("namespace", &self.namespace)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,15 +1,28 @@
 use crate::namespace::{
-    connection::*, heartbeat::*, media::*, multizone::*, receiver::*, Custom, NamespaceUrn,
+    connection::*, device_auth::*, heartbeat::*, media::*, multizone::*, receiver::*, Custom,
+    CustomBinary, NamespaceUrn,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum Payload {
     Connection(Connection),
     Heartbeat(Heartbeat),
     Media(Media),
     Multizone(Multizone),
     Receiver(Receiver),
+    // Binary payloads, see `client::decode_message`
+    #[serde(skip)]
+    DeviceAuth(DeviceAuthMessage),
+    #[serde(skip)]
+    CustomBinary(CustomBinary),
+    // Payloads which don't match the types of their namespace
+    #[serde(skip)]
+    Unknown {
+        namespace: NamespaceUrn,
+        raw_json: String,
+    },
     // Fallback -> Needs to be last enum variant!
     Custom(Custom),
 }
@@ -22,6 +35,9 @@ impl Payload {
             Payload::Media(_) => NamespaceUrn::Media,
             Payload::Multizone(_) => NamespaceUrn::Multizone,
             Payload::Receiver(_) => NamespaceUrn::Receiver,
+            Payload::DeviceAuth(_) => NamespaceUrn::DeviceAuth,
+            Payload::CustomBinary(pl) => pl.namespace.clone(),
+            Payload::Unknown { namespace, .. } => namespace.clone(),
             Payload::Custom(pl) => pl.namespace.clone(),
         }
     }
@@ -1,2 +1,3 @@
+#[allow(dead_code)]
 mod cast;
 pub use cast::*;
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use smol::lock::Mutex;
+
+use crate::namespace::media::*;
+use crate::receiver::spawn_event_sync;
+use crate::{Error, MediaController, Payload};
+
+#[derive(Debug, Default)]
+struct QueueState {
+    item_ids: Vec<i32>,
+    items: HashMap<i32, QueueItem>,
+    current_item_id: Option<i32>,
+    repeat_mode: RepeatMode,
+}
+
+impl QueueState {
+    fn set_item_ids(&mut self, item_ids: Vec<i32>) {
+        self.items.retain(|id, _| item_ids.contains(id));
+        self.item_ids = item_ids;
+    }
+
+    fn set_items(&mut self, items: &[QueueItem]) {
+        for item in items {
+            if let Some(item_id) = item.item_id {
+                self.items.insert(item_id, item.clone());
+            }
+        }
+    }
+
+    fn missing_items(&self) -> Vec<i32> {
+        self.item_ids
+            .iter()
+            .filter(|id| !self.items.contains_key(id))
+            .copied()
+            .collect()
+    }
+}
+
+// Shared by all clones of a controller, the sync task only holds a weak reference
+#[derive(Debug)]
+struct Queue {
+    media_controller: MediaController,
+    state: Mutex<QueueState>,
+}
+
+/// Manages the queue of a media session, and keeps a local copy of it which gets
+/// updated with the `QUEUE_CHANGE` messages the receiver sends to all senders.
+#[derive(Clone, Debug)]
+pub struct QueueController {
+    queue: Arc<Queue>,
+}
+
+impl QueueController {
+    pub fn new(media_controller: MediaController) -> Self {
+        let events = media_controller
+            .receiver()
+            .transport_events(media_controller.app().transport_id.clone());
+        let controller = Self {
+            queue: Arc::new(Queue {
+                media_controller,
+                state: Mutex::default(),
+            }),
+        };
+        spawn_event_sync(
+            events,
+            Arc::downgrade(&controller.queue),
+            |queue, response| async move {
+                if let Payload::Media(media) = response.payload {
+                    let controller = QueueController { queue };
+                    if let Err(err) = controller.process(media).await {
+                        warn!("Unable to synchronize queue: {}", err);
+                    }
+                }
+            },
+        );
+        controller
+    }
+
+    /// All items of the queue in playback order. Items whose details
+    /// haven't been fetched yet only contain the `item_id`.
+    pub async fn items(&self) -> Vec<QueueItem> {
+        let state = self.queue.state.lock().await;
+        state
+            .item_ids
+            .iter()
+            .map(|id| {
+                state.items.get(id).cloned().unwrap_or(QueueItem {
+                    item_id: Some(*id),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    pub async fn item_ids(&self) -> Vec<i32> {
+        self.queue.state.lock().await.item_ids.clone()
+    }
+
+    pub async fn current_item_id(&self) -> Option<i32> {
+        self.queue.state.lock().await.current_item_id
+    }
+
+    pub async fn repeat_mode(&self) -> RepeatMode {
+        self.queue.state.lock().await.repeat_mode.clone()
+    }
+
+    /// Replaces the current media session with a new queue, starting playback at `start_index`
+    pub async fn load(
+        &self,
+        items: Vec<QueueItem>,
+        start_index: Option<i32>,
+        repeat_mode: Option<RepeatMode>,
+    ) -> Result<MediaStatus, Error> {
+        let status = self
+            .queue
+            .media_controller
+            .send_media_request(Media::QueueLoad(QueueLoadRequestData {
+                items,
+                repeat_mode,
+                start_index,
+                ..Default::default()
+            }))
+            .await?;
+
+        *self.queue.state.lock().await = QueueState::default();
+        self.process_status(&status).await;
+        self.refresh().await?;
+
+        Ok(status)
+    }
+
+    /// Inserts the items before the item with the id `insert_before`, or appends them
+    pub async fn insert(
+        &self,
+        items: Vec<QueueItem>,
+        insert_before: Option<i32>,
+    ) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueInsert(QueueInsertRequestData {
+            insert_before,
+            items,
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    pub async fn remove(&self, item_ids: Vec<i32>) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueRemove(QueueRemoveRequestData {
+            item_ids,
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Moves the items before the item with the id `insert_before`, or to the end of the queue
+    pub async fn reorder(
+        &self,
+        item_ids: Vec<i32>,
+        insert_before: Option<i32>,
+    ) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueReorder(QueueReorderRequestData {
+            insert_before,
+            item_ids,
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Replaces the details of existing items, matched by their `item_id`
+    pub async fn update(&self, items: Vec<QueueItem>) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueUpdate(QueueUpdateRequestData {
+            items: Some(items),
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Skips `offset` items forward, or backwards if negative
+    pub async fn jump(&self, offset: i32) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueUpdate(QueueUpdateRequestData {
+            jump: Some(offset),
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Starts playing the item with the given id
+    pub async fn jump_to(&self, item_id: i32) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueUpdate(QueueUpdateRequestData {
+            current_item_id: Some(item_id),
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    pub async fn next(&self) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueNext(RequestData {
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+        }))
+        .await
+    }
+
+    pub async fn prev(&self) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueuePrev(RequestData {
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+        }))
+        .await
+    }
+
+    pub async fn shuffle(&self) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueUpdate(QueueUpdateRequestData {
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            shuffle: Some(true),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    pub async fn set_repeat_mode(&self, repeat_mode: RepeatMode) -> Result<MediaStatus, Error> {
+        self.send_queue_request(Media::QueueUpdate(QueueUpdateRequestData {
+            media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            repeat_mode: Some(repeat_mode),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Fetches the complete queue again, e.g. if queue changes might have been missed
+    pub async fn refresh(&self) -> Result<(), Error> {
+        let response = self
+            .queue
+            .media_controller
+            .send_request(Media::QueueGetItemIds(RequestData {
+                media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            }))
+            .await?;
+        MediaController::handle_error(&response)?;
+
+        if let Payload::Media(Media::QueueItemIds(ids)) = response.payload {
+            self.set_item_ids(ids).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_queue_request(&self, payload: Media) -> Result<MediaStatus, Error> {
+        let status = self
+            .queue
+            .media_controller
+            .send_media_request(payload)
+            .await?;
+        self.process_status(&status).await;
+        Ok(status)
+    }
+
+    async fn fetch_items(&self, item_ids: Vec<i32>) -> Result<(), Error> {
+        if item_ids.is_empty() {
+            return Ok(());
+        }
+
+        let response = self
+            .queue
+            .media_controller
+            .send_request(Media::QueueGetItems(GetItemsInfoRequestData {
+                item_ids,
+                media_session_id: Some(self.queue.media_controller.media_session_id().await?),
+            }))
+            .await?;
+        MediaController::handle_error(&response)?;
+
+        if let Payload::Media(Media::QueueItems(info)) = response.payload {
+            let mut state = self.queue.state.lock().await;
+            state.set_items(&info.items.unwrap_or_default());
+        }
+
+        Ok(())
+    }
+
+    async fn set_item_ids(&self, ids: QueueIds) -> Result<(), Error> {
+        let missing = {
+            let mut state = self.queue.state.lock().await;
+            state.set_item_ids(ids.item_ids.unwrap_or_default());
+            state.missing_items()
+        };
+        self.fetch_items(missing).await
+    }
+
+    async fn process(&self, media: Media) -> Result<(), Error> {
+        match media {
+            Media::QueueItemIds(ids) => self.set_item_ids(ids).await?,
+            Media::QueueItems(info) => {
+                let mut state = self.queue.state.lock().await;
+                state.set_items(&info.items.unwrap_or_default());
+            }
+            Media::QueueChange(change) => self.process_change(change).await?,
+            Media::MediaStatus(response_data) => {
+                if let Some(status) = response_data.status.first() {
+                    self.process_status(status).await;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    async fn process_change(&self, change: QueueChange) -> Result<(), Error> {
+        let item_ids = change.item_ids.unwrap_or_default();
+
+        // Reordered items aren't listed with their new position
+        if change.reorder_item_ids.is_some() {
+            return self.refresh().await;
+        }
+
+        match change.change_type.unwrap_or(QueueChangeType::NoChange) {
+            QueueChangeType::Insert => {
+                {
+                    let mut state = self.queue.state.lock().await;
+                    let position = change
+                        .insert_before
+                        .and_then(|before| state.item_ids.iter().position(|id| *id == before))
+                        .unwrap_or(state.item_ids.len());
+
+                    let new_ids: Vec<i32> = item_ids
+                        .iter()
+                        .filter(|id| !state.item_ids.contains(id))
+                        .copied()
+                        .collect();
+                    state.item_ids.splice(position..position, new_ids);
+                }
+                self.fetch_items(item_ids).await?;
+            }
+            QueueChangeType::Remove => {
+                let mut state = self.queue.state.lock().await;
+                state.item_ids.retain(|id| !item_ids.contains(id));
+                state.items.retain(|id, _| !item_ids.contains(id));
+            }
+            QueueChangeType::ItemsChange => self.fetch_items(item_ids).await?,
+            QueueChangeType::Update => self.refresh().await?,
+            QueueChangeType::NoChange => (),
+        }
+
+        Ok(())
+    }
+
+    async fn process_status(&self, status: &MediaStatus) {
+        let mut state = self.queue.state.lock().await;
+        state.current_item_id = status.current_item_id;
+        if let Some(repeat_mode) = &status.repeat_mode {
+            state.repeat_mode = repeat_mode.clone();
+        }
+        if let Some(items) = &status.items {
+            state.set_items(items);
+        }
+    }
+}
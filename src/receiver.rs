@@ -1,73 +1,388 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
+use async_broadcast::InactiveReceiver;
 use async_channel::Sender;
-use smol::lock::Mutex;
+use futures_util::future::{self, BoxFuture};
+use futures_util::{Stream, StreamExt};
+use smol::lock::{Mutex, RwLock};
 use smol_timeout::TimeoutExt;
 
 use crate::app::AppId;
+use crate::device_auth::{self, DeviceIdentity};
+use crate::error::RequestErrorKind;
 use crate::namespace::{
     connection::*,
     heartbeat::*,
+    media::DeviceCapabilities,
     receiver::{self, *},
     NamespaceUrn,
 };
-use crate::{App, Response, Volume};
+use crate::{
+    App, AuthError, CastAddr, HeartbeatConfig, MediaController, ReceiverConfig, Response,
+    ToCastAddr, Volume,
+};
+
+use super::{Client, Error, Payload, DEFAULT_SENDER_ID};
+
+type ResponseSender = Sender<Result<Response, Error>>;
+
+// Number of messages buffered for each event subscriber before the oldest ones get dropped
+pub(crate) const EVENTS_CAPACITY: usize = 64;
+
+/// Broadcast channel for events, which drops the oldest messages of subscribers falling
+/// behind. The inactive receiver keeps the channel open while nobody is subscribed.
+pub(crate) fn event_channel<T: Clone>() -> (async_broadcast::Sender<T>, InactiveReceiver<T>) {
+    let (mut events, events_rx) = async_broadcast::broadcast(EVENTS_CAPACITY);
+    events.set_overflow(true);
+    events.set_await_active(false);
+    (events, events_rx.deactivate())
+}
+
+/// Passes the unsolicited messages of `events` to `target` on a background task, until
+/// `target` got dropped. Responses to requests are left to whoever sent the request.
+pub(crate) fn spawn_event_sync<T, F, Fut>(
+    mut events: impl Stream<Item = Response> + Unpin + Send + 'static,
+    target: Weak<T>,
+    process: F,
+) where
+    T: Send + Sync + 'static,
+    F: Fn(Arc<T>, Response) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    smol::spawn(async move {
+        while let Some(response) = events.next().await {
+            // Stop once all controllers got dropped
+            let Some(target) = target.upgrade() else {
+                break;
+            };
+
+            // Responses to own requests are already processed
+            if matches!(response.request_id, None | Some(0)) {
+                process(target, response).await;
+            }
+        }
+    })
+    .detach();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// Trying to restore a lost connection, see [`crate::ReconnectPolicy`]
+    Reconnecting {
+        attempt: u32,
+    },
+    Disconnected(DisconnectReason),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Never connected, or [`Receiver::disconnect`] got called
+    Requested,
+    ConnectionFailed(String),
+    ConnectionLost(String),
+    /// No message arrived within [`crate::HeartbeatConfig::timeout`]
+    HeartbeatTimeout,
+    /// Gave up after the maximum number of reconnect attempts
+    ReconnectFailed,
+}
+
+/// Virtual connection between one of our sender ids and a transport on the receiver
+#[derive(Debug, Clone)]
+struct VirtualConnection {
+    sender_id: String,
+    app: App,
+}
+
+// Closes the virtual connections of a sender id once all handles using it got dropped,
+// and forgets the ones the receiver closed
+#[derive(Debug)]
+struct SenderId {
+    id: String,
+    client: Arc<RwLock<Option<Client>>>,
+    connections: Arc<Mutex<Vec<VirtualConnection>>>,
+    closed_connections: Arc<Mutex<HashSet<(String, String)>>>,
+}
+
+impl Drop for SenderId {
+    fn drop(&mut self) {
+        let id = std::mem::take(&mut self.id);
+        let client = self.client.clone();
+        let connections = self.connections.clone();
+        let closed_connections = self.closed_connections.clone();
+
+        smol::spawn(async move {
+            closed_connections
+                .lock()
+                .await
+                .retain(|(sender_id, _)| *sender_id != id);
+
+            let closed: Vec<_> = {
+                let mut connections = connections.lock().await;
+                let (closed, open) = connections.drain(..).partition(|c| c.sender_id == id);
+                *connections = open;
+                closed
+            };
+
+            let Some(client) = client.read().await.clone() else {
+                return;
+            };
+            for connection in closed.into_iter().rev() {
+                let close = client
+                    .send_from(
+                        id.clone(),
+                        connection.app.transport_id,
+                        Connection::Close,
+                        None,
+                    )
+                    .await;
+                if let Err(err) = close {
+                    debug!("Unable to close virtual connection of {}: {}", id, err);
+                }
+            }
+        })
+        .detach();
+    }
+}
 
-use super::{Client, Error, Payload};
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    /// The receiver closed the virtual connection to the app, e.g. because it got
+    /// stopped by another sender. Sending to it fails with [`Error::AppNotRunning`].
+    AppClosed(App),
+}
 
 #[derive(Debug, Clone)]
 pub struct Receiver {
-    client: Arc<Mutex<Option<Client>>>,
+    // Only written when (re)connecting, sending just needs a cheap clone of the handle
+    client: Arc<RwLock<Option<Client>>>,
     platform: App,
+    config: ReceiverConfig,
+
+    // Remembered for reconnecting
+    addr: Arc<Mutex<Option<CastAddr>>>,
+    // Source of sent messages, and destination of received ones
+    sender_id: Arc<SenderId>,
+    // Established virtual connections of all sender ids, which get restored when reconnecting
+    connections: Arc<Mutex<Vec<VirtualConnection>>>,
+    // Virtual connections, as (sender id, transport id), which got closed by the receiver
+    closed_connections: Arc<Mutex<HashSet<(String, String)>>>,
+    // Gets incremented with every (re)connect, so that
+    // connection specific state can be detected as outdated
+    connection_id: Arc<Mutex<u32>>,
 
     // Ids for request messages which get incremented
-    request_id: Arc<Mutex<u32>>,
-    requests: Arc<Mutex<HashMap<u32, Sender<Response>>>>,
+    request_id: Arc<AtomicU32>,
+    requests: Arc<Mutex<HashMap<u32, ResponseSender>>>,
+    // Used by the heartbeat to detect dead connections
+    last_message: Arc<Mutex<Instant>>,
+
+    events: async_broadcast::Sender<Response>,
+    // Keeps the events channel open while nobody is subscribed
+    _events_rx: InactiveReceiver<Response>,
+
+    state: Arc<Mutex<ConnectionState>>,
+    state_events: async_broadcast::Sender<ConnectionState>,
+    _state_events_rx: InactiveReceiver<ConnectionState>,
+
+    // Tagged with the sender id whose virtual connection the event is about
+    app_events: async_broadcast::Sender<(String, AppEvent)>,
+    _app_events_rx: InactiveReceiver<(String, AppEvent)>,
 }
 
 impl Receiver {
     pub fn new() -> Self {
+        Self::with_config(ReceiverConfig::default())
+    }
+
+    pub fn with_config(config: ReceiverConfig) -> Self {
+        let (events, events_rx) = event_channel();
+        let (state_events, state_events_rx) = event_channel();
+        let (app_events, app_events_rx) = event_channel();
+
+        let client = Arc::<RwLock<Option<Client>>>::default();
+        let platform = App::receiver();
+        let connections = Arc::new(Mutex::new(vec![VirtualConnection {
+            sender_id: DEFAULT_SENDER_ID.into(),
+            app: platform.clone(),
+        }]));
+        let closed_connections = Arc::<Mutex<HashSet<(String, String)>>>::default();
+        let sender_id = Arc::new(SenderId {
+            id: DEFAULT_SENDER_ID.into(),
+            client: client.clone(),
+            connections: connections.clone(),
+            closed_connections: closed_connections.clone(),
+        });
+
         Self {
-            client: Arc::default(),
-            platform: App::receiver(),
+            client,
+            platform,
+            config,
+            addr: Arc::default(),
+            sender_id,
+            connections,
+            closed_connections,
+            connection_id: Arc::default(),
             request_id: Arc::default(),
             requests: Arc::default(),
+            last_message: Arc::new(Mutex::new(Instant::now())),
+            events,
+            _events_rx: events_rx,
+            state: Arc::new(Mutex::new(ConnectionState::Disconnected(
+                DisconnectReason::Requested,
+            ))),
+            state_events,
+            _state_events_rx: state_events_rx,
+            app_events,
+            _app_events_rx: app_events_rx,
+        }
+    }
+
+    pub async fn connect<A: ToCastAddr>(&self, addr: A) -> Result<(), Error> {
+        let addr = addr.to_cast_addr()?;
+        self.addr.lock().await.replace(addr.clone());
+        self.clear_app_connections().await;
+        if let Some(client) = self.client.write().await.take() {
+            client.close();
+        }
+
+        self.set_state(ConnectionState::Connecting).await;
+        match self.establish(&addr).await {
+            Ok(()) => {
+                self.set_state(ConnectionState::Connected).await;
+                Ok(())
+            }
+            Err(err) => {
+                self.addr.lock().await.take();
+                self.set_state(ConnectionState::Disconnected(
+                    DisconnectReason::ConnectionFailed(err.to_string()),
+                ))
+                .await;
+                Err(err)
+            }
         }
     }
 
-    pub async fn connect(&self, addr: &str) -> Result<(), Error> {
+    /// Only closes the underlying connection, does not stop any running applications.
+    /// Affects all sender ids sharing the connection, see [`Receiver::new_sender`].
+    pub async fn disconnect(&self) {
+        // Try to close the virtual connections, but don't care about the result
+        if let Some(client) = self.client().await {
+            let connections = self.connections.lock().await.clone();
+            for connection in connections.into_iter().rev() {
+                let _ = client
+                    .send_from(
+                        connection.sender_id,
+                        connection.app.transport_id,
+                        Connection::Close,
+                        None,
+                    )
+                    .await;
+            }
+        }
+
+        self.addr.lock().await.take();
+        self.clear_app_connections().await;
+        if let Some(client) = self.client.write().await.take() {
+            client.close();
+        }
+
+        // Pending requests can't get answered anymore
+        self.fail_requests(|| Error::NoConnection).await;
+
+        // Reset requestId counter
+        self.request_id.store(0, Ordering::Relaxed);
+
+        self.set_state(ConnectionState::Disconnected(DisconnectReason::Requested))
+            .await;
+    }
+
+    /// Opens the connection and the virtual connections of all sender ids
+    /// to the platform and previously connected apps, then starts receiving messages.
+    async fn establish(&self, addr: &CastAddr) -> Result<(), Error> {
         let client = Client::connect(addr).await?;
-        self.client.lock().await.replace(client.clone());
+        self.client.write().await.replace(client.clone());
 
-        // Establish virtual connection with cast receiver
-        self.send(&self.platform, Connection::Connect).await?;
+        let result: Result<(), Error> = async {
+            // Establish virtual connection with cast receiver
+            client
+                .send(
+                    self.platform.transport_id.clone(),
+                    Connection::Connect,
+                    None,
+                )
+                .await?;
 
-        // Ensure we're successfully connected by doing a ping <-> pong sequence
-        self.send(&self.platform, Heartbeat::Ping).await?;
-        client.receive().await?;
+            // Ensure we're successfully connected by doing a ping <-> pong sequence
+            client
+                .send(self.platform.transport_id.clone(), Heartbeat::Ping, None)
+                .await?;
+            client
+                .receive()
+                .timeout(self.config.request_timeout)
+                .await
+                .ok_or_else(|| {
+                    Error::response_timeout(
+                        NamespaceUrn::Heartbeat,
+                        None,
+                        &self.platform.transport_id,
+                    )
+                })??;
+
+            let connections = self.connections.lock().await.clone();
+            for connection in connections {
+                let is_default = connection.sender_id == DEFAULT_SENDER_ID
+                    && connection.app.transport_id == self.platform.transport_id;
+                if !is_default {
+                    client
+                        .send_from(
+                            connection.sender_id,
+                            connection.app.transport_id,
+                            Connection::Connect,
+                            None,
+                        )
+                        .await?;
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            self.client.write().await.take();
+            client.close();
+            return Err(err);
+        }
+
+        *self.connection_id.lock().await += 1;
+        *self.last_message.lock().await = Instant::now();
+
+        if let Some(heartbeat) = self.config.heartbeat.clone() {
+            self.spawn_heartbeat(client.clone(), heartbeat);
+        }
 
         // Spawn own task to receive messages from the receiver
         let d = self.clone();
         smol::spawn(async move {
             loop {
-                if let Some(client) = d.client().await {
-                    match client.receive().await {
-                        Ok(response) => {
-                            if let Err(err) = d.process_response(response).await {
-                                warn!("Unable to process received message: {}", err.to_string())
-                            }
-                        }
-                        Err(err) => {
-                            error!("Unable to receive message: {}", err.to_string());
-                            d.disconnect().await;
-                            break;
+                match client.receive().await {
+                    Ok(response) => {
+                        *d.last_message.lock().await = Instant::now();
+                        if let Err(err) = d.process_response(response).await {
+                            warn!("Unable to process received message: {}", err)
                         }
                     }
-                } else {
-                    debug!("No client available, stop receiving.");
-                    break;
+                    // Undecodable frames are skipped, so the connection itself is broken
+                    Err(err) => {
+                        d.connection_lost(&client, err).await;
+                        break;
+                    }
                 }
             }
         })
@@ -76,62 +391,403 @@ impl Receiver {
         Ok(())
     }
 
-    /// Only closes the underlying connection, does not stop any running applications.
-    pub async fn disconnect(&self) {
-        // Try to close the virtual connection, but don't care about the result
-        let _ = self.send(&self.platform, Connection::Close).await;
+    // Boxed, as the future is indirectly recursive through `establish()`
+    fn connection_lost<'a>(&'a self, client: &'a Client, err: Error) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            {
+                let mut current = self.client.write().await;
+                if !current.as_ref().is_some_and(|c| c.is_same(client)) {
+                    debug!("Stop receiving from closed connection: {}", err);
+                    return;
+                }
+                current.take();
+            }
 
-        let mut client = self.client.lock().await;
-        *client = None;
+            error!("Connection lost: {}", err);
+            client.close();
 
-        // Reset requestId counter
-        *self.request_id.lock().await = 0;
+            let reason = if let Error::HeartbeatTimeout = err {
+                self.fail_requests(|| Error::HeartbeatTimeout).await;
+                DisconnectReason::HeartbeatTimeout
+            } else {
+                self.fail_requests(|| Error::NoConnection).await;
+                DisconnectReason::ConnectionLost(err.to_string())
+            };
+
+            let Some(policy) = self.config.reconnect_policy.clone() else {
+                self.addr.lock().await.take();
+                self.set_state(ConnectionState::Disconnected(reason)).await;
+                return;
+            };
+
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                if policy.max_attempts.is_some_and(|max| attempt > max) {
+                    self.addr.lock().await.take();
+                    self.set_state(ConnectionState::Disconnected(
+                        DisconnectReason::ReconnectFailed,
+                    ))
+                    .await;
+                    return;
+                }
+
+                self.set_state(ConnectionState::Reconnecting { attempt })
+                    .await;
+                smol::Timer::after(policy.delay(attempt)).await;
+
+                // Stop trying if `disconnect()` got called in the meantime
+                let Some(addr) = self.addr.lock().await.clone() else {
+                    return;
+                };
+
+                match self.establish(&addr).await {
+                    Ok(()) => {
+                        info!("Reconnected to {} after {} attempt(s)", addr, attempt);
+                        self.set_state(ConnectionState::Connected).await;
+                        return;
+                    }
+                    Err(err) => warn!("Reconnect attempt {} failed: {}", attempt, err),
+                }
+            }
+        })
+    }
+
+    fn spawn_heartbeat(&self, client: Client, config: HeartbeatConfig) {
+        let d = self.clone();
+        smol::spawn(async move {
+            loop {
+                smol::Timer::after(config.interval).await;
+
+                let is_current = d
+                    .client()
+                    .await
+                    .is_some_and(|current| current.is_same(&client));
+                if !is_current {
+                    break;
+                }
+
+                if d.last_message.lock().await.elapsed() > config.timeout {
+                    d.connection_lost(&client, Error::HeartbeatTimeout).await;
+                    break;
+                }
+
+                let ping = client
+                    .send(d.platform.transport_id.clone(), Heartbeat::Ping, None)
+                    .await;
+                if let Err(err) = ping {
+                    debug!("Unable to send heartbeat: {}", err);
+                }
+            }
+        })
+        .detach();
+    }
+
+    async fn fail_requests(&self, err: impl Fn() -> Error) {
+        for (_, sender) in self.requests.lock().await.drain() {
+            let _ = sender.try_send(Err(err()));
+        }
+    }
+
+    pub async fn connection_state(&self) -> ConnectionState {
+        self.state.lock().await.clone()
+    }
+
+    /// Changes of the [`ConnectionState`]
+    pub fn connection_events(&self) -> impl Stream<Item = ConnectionState> + Unpin + Send {
+        self.state_events.new_receiver()
+    }
+
+    async fn set_state(&self, state: ConnectionState) {
+        let mut current = self.state.lock().await;
+        if *current != state {
+            debug!("Connection state: {:?}", state);
+            *current = state.clone();
+            let _ = self.state_events.try_broadcast(state);
+        }
+    }
+
+    /// Increments whenever a new connection got established, e.g. after reconnecting
+    pub(crate) async fn connection_id(&self) -> u32 {
+        *self.connection_id.lock().await
+    }
+
+    /// Opens another virtual connection with a new `sender-<uuid>` id on the same connection,
+    /// so that independent components can't interfere with each other. The returned
+    /// receiver only gets messages addressed to its sender id, or broadcasted to all
+    /// senders. Its virtual connections get closed once all its clones got dropped.
+    pub async fn new_sender(&self) -> Result<Receiver, Error> {
+        let id = format!("sender-{}", uuid::Uuid::new_v4());
+        let sender_id = Arc::new(SenderId {
+            id: id.clone(),
+            client: self.client.clone(),
+            connections: self.connections.clone(),
+            closed_connections: self.closed_connections.clone(),
+        });
+
+        self.connections.lock().await.push(VirtualConnection {
+            sender_id: id,
+            app: self.platform.clone(),
+        });
+
+        let receiver = Receiver {
+            sender_id,
+            ..self.clone()
+        };
+        if let Some(client) = receiver.client().await {
+            client
+                .send_from(
+                    receiver.sender_id().to_string(),
+                    receiver.platform.transport_id.clone(),
+                    Connection::Connect,
+                    None,
+                )
+                .await?;
+        }
+
+        Ok(receiver)
+    }
+
+    pub(crate) fn config(&self) -> &ReceiverConfig {
+        &self.config
+    }
+
+    /// Capabilities of the connected receiver, if they are known from [`CastAddr::capabilities`]
+    pub async fn capabilities(&self) -> Option<DeviceCapabilities> {
+        self.addr.lock().await.as_ref()?.capabilities()
+    }
+
+    /// The platform app, which handles the receiver and multizone namespaces
+    pub(crate) fn platform(&self) -> &App {
+        &self.platform
+    }
+
+    /// Source id of the sent messages, [`DEFAULT_SENDER_ID`] unless created with [`Receiver::new_sender`]
+    pub fn sender_id(&self) -> &str {
+        &self.sender_id.id
+    }
+
+    // Virtual connections to the platform are kept, as every sender id needs one
+    async fn clear_app_connections(&self) {
+        let platform = &self.platform.transport_id;
+        self.connections
+            .lock()
+            .await
+            .retain(|connection| connection.app.transport_id == *platform);
+        self.closed_connections.lock().await.clear();
+    }
+
+    /// Apps whose virtual connection of this sender id got closed by the receiver, see [`AppEvent`]
+    pub fn app_events(&self) -> impl Stream<Item = AppEvent> + Unpin + Send {
+        let sender_id = self.sender_id.id.clone();
+        self.app_events
+            .new_receiver()
+            .filter_map(move |(id, event)| future::ready((id == sender_id).then_some(event)))
+    }
+
+    /// Whether the receiver closed the virtual connection of this sender id to the app
+    pub async fn is_app_closed(&self, app: &App) -> bool {
+        self.closed_connections
+            .lock()
+            .await
+            .contains(&(self.sender_id.id.clone(), app.transport_id.clone()))
+    }
+
+    async fn app_closed(&self, response: &Response) {
+        let transport_id = &response.source_id;
+        let closed: Vec<_> = {
+            let mut connections = self.connections.lock().await;
+            let (closed, open) = connections.drain(..).partition(|c| {
+                c.app.transport_id == *transport_id
+                    && (response.destination_id == "*" || c.sender_id == response.destination_id)
+            });
+            *connections = open;
+            closed
+        };
+
+        let mut closed_connections = self.closed_connections.lock().await;
+        if response.destination_id != "*" {
+            closed_connections.insert((response.destination_id.clone(), transport_id.clone()));
+        }
+
+        for connection in closed {
+            info!(
+                "Receiver closed the connection of {} to {}",
+                connection.sender_id, transport_id
+            );
+            closed_connections.insert((connection.sender_id.clone(), transport_id.clone()));
+            let _ = self
+                .app_events
+                .try_broadcast((connection.sender_id, AppEvent::AppClosed(connection.app)));
+        }
     }
 
     pub async fn is_connected(&self) -> bool {
         self.client().await.is_some()
     }
 
+    /// All messages received from the receiver for this sender id, including responses to
+    /// requests and unsolicited status broadcasts. Each subscriber gets its own copy of every
+    /// message, subscribers which fall behind lose the oldest messages.
+    pub fn events(&self) -> impl Stream<Item = Response> + Unpin + Send {
+        let sender_id = self.sender_id.id.clone();
+        self.events.new_receiver().filter(move |response| {
+            future::ready(response.destination_id == sender_id || response.destination_id == "*")
+        })
+    }
+
+    /// Received messages of the given namespace, see [`Receiver::events`]
+    pub fn namespace_events(
+        &self,
+        namespace: NamespaceUrn,
+    ) -> impl Stream<Item = Response> + Unpin + Send {
+        self.events()
+            .filter(move |response| future::ready(response.namespace == namespace))
+    }
+
+    /// Received messages sent by the given transport id, e.g. a launched [`App`], see [`Receiver::events`]
+    pub fn transport_events(
+        &self,
+        transport_id: impl Into<String>,
+    ) -> impl Stream<Item = Response> + Unpin + Send {
+        let transport_id = transport_id.into();
+        self.events()
+            .filter(move |response| future::ready(response.source_id == transport_id))
+    }
+
     /// Currently running applications
     pub async fn applications(&self) -> Result<Vec<App>, Error> {
         Ok(self.status().await?.applications.unwrap_or_default())
     }
 
     pub async fn launch_app(&self, app_id: AppId) -> Result<App, Error> {
+        self.launch_app_with_timeout(app_id, self.config.request_timeout)
+            .await
+    }
+
+    /// Same as [`Receiver::launch_app`], but waits up to `timeout` for the app to start
+    pub async fn launch_app_with_timeout(
+        &self,
+        app_id: AppId,
+        timeout: Duration,
+    ) -> Result<App, Error> {
         let response = self
-            .send_request(
+            .send_request_with_timeout(
                 &self.platform,
                 receiver::Receiver::launch_request(app_id.clone()),
+                timeout,
             )
             .await?;
 
-        if let Payload::Receiver(payload) = response.payload {
-            if let receiver::Receiver::LaunchError(LaunchErrorResponse { reason }) = payload {
-                return Err(Error::LaunchError(reason));
-            }
+        Self::handle_error(&response)?;
 
-            if let receiver::Receiver::ReceiverStatus(ReceiverStatusResponse { status }) = payload {
-                if let Some(apps) = status.applications {
-                    for app in apps {
-                        if app.app_id == app_id {
-                            // Establish new virtual connection to be able to send/receive app specific payloads
-                            self.send(&app, Connection::Connect).await?;
-                            return Ok(app);
-                        }
-                    }
-                }
+        if let Payload::Receiver(receiver::Receiver::ReceiverStatus(ReceiverStatusResponse {
+            status,
+        })) = &response.payload
+        {
+            let apps = status.applications.iter().flatten();
+            if let Some(app) = apps.into_iter().find(|app| app.app_id == app_id) {
+                self.connect_app(app).await?;
+                return Ok(app.clone());
             }
         }
 
-        Err(Error::NoResponse)
+        Err(Error::unexpected_response(response))
+    }
+
+    /// Controls the media of an already running app, without restarting it like [`Receiver::launch_app`]
+    pub async fn join_app(&self, app_id: AppId) -> Result<MediaController, Error> {
+        self.join(|app| app.app_id == app_id).await
+    }
+
+    /// Same as [`Receiver::join_app`], but for the app with the given session id
+    pub async fn join_session(&self, session_id: &str) -> Result<MediaController, Error> {
+        self.join(|app| app.session_id == session_id).await
+    }
+
+    async fn join(&self, predicate: impl Fn(&App) -> bool) -> Result<MediaController, Error> {
+        let app = self
+            .applications()
+            .await?
+            .into_iter()
+            .find(predicate)
+            .ok_or(Error::AppNotRunning)?;
+
+        let media_controller = MediaController::new(app.clone(), self.clone())?;
+        self.connect_app(&app).await?;
+
+        // Apps may be idle, the media session id gets resolved once a request needs it
+        media_controller.fetch_status().await?;
+
+        Ok(media_controller)
+    }
+
+    /// Establishes a virtual connection to be able to send/receive app specific payloads
+    async fn connect_app(&self, app: &App) -> Result<(), Error> {
+        self.closed_connections
+            .lock()
+            .await
+            .remove(&(self.sender_id.id.clone(), app.transport_id.clone()));
+        self.send(app, Connection::Connect).await?;
+
+        let mut connections = self.connections.lock().await;
+        connections
+            .retain(|c| c.sender_id != self.sender_id.id || c.app.transport_id != app.transport_id);
+        connections.push(VirtualConnection {
+            sender_id: self.sender_id.id.clone(),
+            app: app.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Whether the apps can be launched on the receiver. Apps the receiver
+    /// doesn't know are reported as [`Availability::AppUnavailable`].
+    pub async fn app_availability(
+        &self,
+        app_ids: &[AppId],
+    ) -> Result<HashMap<AppId, Availability>, Error> {
+        let response = self
+            .send_request(
+                &self.platform,
+                receiver::Receiver::app_availability_request(app_ids),
+            )
+            .await?;
+        Self::handle_error(&response)?;
+
+        let Payload::Receiver(receiver::Receiver::GetAppAvailability(AppAvailability {
+            availability: Some(availability),
+            ..
+        })) = &response.payload
+        else {
+            return Err(Error::unexpected_response(response));
+        };
+
+        Ok(app_ids
+            .iter()
+            .map(|app_id| {
+                let state = availability
+                    .get(&app_id.to_string())
+                    .copied()
+                    .unwrap_or(Availability::AppUnavailable);
+                (app_id.clone(), state)
+            })
+            .collect())
     }
 
     pub async fn stop_app(&self, app: &App) -> Result<(), Error> {
-        self.send_request(
-            &self.platform,
-            receiver::Receiver::stop_request(app.session_id.clone()),
-        )
-        .await?;
+        let response = self
+            .send_request(
+                &self.platform,
+                receiver::Receiver::stop_request(app.session_id.clone()),
+            )
+            .await?;
+        Self::handle_error(&response)?;
+
+        self.connections
+            .lock()
+            .await
+            .retain(|c| c.app.transport_id != app.transport_id);
         Ok(())
     }
 
@@ -140,12 +796,13 @@ impl Receiver {
     }
 
     pub async fn set_volume(&self, level: f64, muted: bool) -> Result<(), Error> {
-        self.send_request(
-            &self.platform,
-            receiver::Receiver::set_volume_request(level, muted),
-        )
-        .await?;
-        Ok(())
+        let response = self
+            .send_request(
+                &self.platform,
+                receiver::Receiver::set_volume_request(level, muted),
+            )
+            .await?;
+        Self::handle_error(&response)
     }
 
     pub async fn status(&self) -> Result<Status, Error> {
@@ -153,6 +810,8 @@ impl Receiver {
             .send_request(&self.platform, receiver::Receiver::GetStatus)
             .await?;
 
+        Self::handle_error(&response)?;
+
         if let Payload::Receiver(receiver::Receiver::ReceiverStatus(ReceiverStatusResponse {
             status,
         })) = response.payload
@@ -160,7 +819,60 @@ impl Receiver {
             return Ok(status);
         }
 
-        Err(Error::NoResponse)
+        Err(Error::unexpected_response(response))
+    }
+
+    fn handle_error(response: &Response) -> Result<(), Error> {
+        let kind = match &response.payload {
+            Payload::Receiver(receiver::Receiver::LaunchError(data)) => {
+                RequestErrorKind::LaunchError(data.clone())
+            }
+            Payload::Receiver(receiver::Receiver::InvalidRequest(data)) => {
+                RequestErrorKind::InvalidRequest(data.clone())
+            }
+            _ => return Ok(()),
+        };
+
+        Err(Error::request(kind, response))
+    }
+
+    /// Verifies that the receiver is a genuine cast device, by challenging it to sign the TLS
+    /// certificate of this connection with a certificate issued by one of the
+    /// [`ReceiverConfig::device_auth_roots`].
+    pub async fn verify_device(&self) -> Result<DeviceIdentity, AuthError> {
+        if self.config.device_auth_roots.is_empty() {
+            warn!("No device auth roots, see `ReceiverConfig::device_auth_roots`");
+            return Err(AuthError::UntrustedChain);
+        }
+
+        let client = self.client().await.ok_or(Error::NoConnection)?;
+        let peer_certificate = client
+            .peer_certificate()
+            .ok_or(AuthError::NoPeerCertificate)?;
+
+        // Device auth messages don't have a request id to match the response
+        let mut responses = self.namespace_events(NamespaceUrn::DeviceAuth);
+        let (challenge, nonce) = device_auth::challenge()?;
+        self.send(&self.platform, challenge).await?;
+
+        let response = responses
+            .next()
+            .timeout(self.config.request_timeout)
+            .await
+            .flatten()
+            .ok_or_else(|| {
+                Error::response_timeout(NamespaceUrn::DeviceAuth, None, &self.platform.transport_id)
+            })?;
+
+        match response.payload {
+            Payload::DeviceAuth(message) => device_auth::verify(
+                message,
+                &nonce,
+                peer_certificate,
+                &self.config.device_auth_roots,
+            ),
+            _ => Err(AuthError::InvalidResponse),
+        }
     }
 
     pub async fn send<P: Into<Payload>>(&self, app: &App, payload: P) -> Result<(), Error> {
@@ -174,6 +886,10 @@ impl Receiver {
             return Err(Error::UnsupportedNamespace);
         }
 
+        if namespace != NamespaceUrn::Connection && self.is_app_closed(app).await {
+            return Err(Error::AppNotRunning);
+        }
+
         let client = match self.client().await {
             Some(client) => client,
             None => {
@@ -181,19 +897,36 @@ impl Receiver {
             }
         };
 
-        client.send(app.transport_id.clone(), payload, None).await?;
+        client
+            .send_from(
+                self.sender_id.id.clone(),
+                app.transport_id.clone(),
+                payload,
+                None,
+            )
+            .await?;
         Ok(())
     }
 
+    /// Sends a request and waits for its response, up to [`ReceiverConfig::request_timeout`]
     pub async fn send_request<P: Into<Payload>>(
         &self,
         app: &App,
         payload: P,
+    ) -> Result<Response, Error> {
+        self.send_request_with_timeout(app, payload, self.config.request_timeout)
+            .await
+    }
+
+    pub async fn send_request_with_timeout<P: Into<Payload>>(
+        &self,
+        app: &App,
+        payload: P,
+        timeout: Duration,
     ) -> Result<Response, Error> {
         let payload: Payload = payload.into();
         let namespace = payload.namespace();
         if !app.namespaces.contains(&namespace) && namespace != NamespaceUrn::Connection {
-            dbg!(&payload);
             debug!(
                 "Unsupported namespace {}, app supports: {:#?}",
                 namespace, app.namespaces
@@ -201,6 +934,10 @@ impl Receiver {
             return Err(Error::UnsupportedNamespace);
         }
 
+        if namespace != NamespaceUrn::Connection && self.is_app_closed(app).await {
+            return Err(Error::AppNotRunning);
+        }
+
         let client = match self.client().await {
             Some(client) => client,
             None => {
@@ -211,11 +948,10 @@ impl Receiver {
         let (response_tx, response_rx) = async_channel::bounded(1);
 
         // Each request message gets a unique requestId
-        let request_id = {
-            let mut id = self.request_id.lock().await;
-            *id += 1;
-            *id
-        };
+        let request_id = self
+            .request_id
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
 
         // Store request to be able to assign the response in `process_message()`
         let mut requests = self.requests.lock().await;
@@ -223,41 +959,71 @@ impl Receiver {
         drop(requests);
 
         client
-            .send(app.transport_id.clone(), payload, Some(request_id))
+            .send_from(
+                self.sender_id.id.clone(),
+                app.transport_id.clone(),
+                payload,
+                Some(request_id),
+            )
             .await?;
 
-        // Wait up to 10 seconds before giving up the request
-        let res = response_rx.recv().timeout(Duration::from_secs(10)).await;
+        let res = response_rx.recv().timeout(timeout).await;
         match res {
-            Some(response) => Ok(response?),
+            Some(response) => response?,
             None => {
                 let mut requests = self.requests.lock().await;
                 requests.remove(&request_id);
-                Err(Error::ResponseTimeout)
+                Err(Error::response_timeout(
+                    namespace,
+                    Some(request_id),
+                    &app.transport_id,
+                ))
             }
         }
     }
 
     async fn process_response(&self, response: Response) -> Result<(), Error> {
+        // Inactive receivers don't matter, and overflowing ones drop the oldest message
+        let _ = self.events.try_broadcast(response.clone());
+
         // Check if this payload is a response to a sent request
         if let Some(request_id) = response.request_id {
             if request_id != 0 {
                 match self.requests.lock().await.remove(&request_id) {
-                    Some(sender) => sender.send(response.clone()).await?,
+                    Some(sender) => {
+                        if sender.send(Ok(response.clone())).await.is_err() {
+                            debug!("Request {} is no longer awaited", request_id);
+                        }
+                    }
                     None => debug!("Ignore payload with unknown requestId"),
                 }
             }
         }
 
+        if let Payload::Connection(Connection::Close) = &response.payload {
+            if response.source_id != self.platform.transport_id {
+                self.app_closed(&response).await;
+            }
+        }
+
+        // Answer from the virtual connection which got pinged
         if let Payload::Heartbeat(Heartbeat::Ping) = &response.payload {
-            self.send(&self.platform, Heartbeat::Pong).await?;
+            let sender_id = match response.destination_id.as_str() {
+                "*" => DEFAULT_SENDER_ID.to_string(),
+                _ => response.destination_id,
+            };
+            if let Some(client) = self.client().await {
+                client
+                    .send_from(sender_id, response.source_id, Heartbeat::Pong, None)
+                    .await?;
+            }
         }
 
         Ok(())
     }
 
     async fn client(&self) -> Option<Client> {
-        self.client.lock().await.clone()
+        self.client.read().await.clone()
     }
 }
 
@@ -1,4 +1,4 @@
-#[derive(Serialize, Deserialize, Clone, Debug, Default, Builder)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Builder)]
 #[serde(rename_all = "camelCase")]
 #[builder(setter(strip_option, into), default)]
 pub struct Volume {
@@ -0,0 +1,74 @@
+use crate::namespace::media::*;
+use crate::{Error, MediaController, Receiver};
+
+/// Moves the media session of `from` to the same app on the receiver `to`, which gets
+/// joined if it's already running there. Apps without stream transfer support get the
+/// media loaded again at the current position, which doesn't include the queue.
+/// The source only gets stopped once playback continues on the target.
+pub async fn transfer_session(
+    from: &MediaController,
+    to: &Receiver,
+) -> Result<MediaController, Error> {
+    let status = from.fetch_status().await?.ok_or(Error::NoMediaSession)?;
+    let position = from
+        .estimated_position()
+        .await
+        .unwrap_or(status.current_time);
+
+    let session_state = if status
+        .supported_media_commands
+        .contains(Command::StreamTransfer)
+    {
+        match from.store_session().await {
+            Ok(session_state) => Some(session_state),
+            Err(err) => {
+                warn!("Unable to store session, loading media again: {}", err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let app_id = from.app().app_id.clone();
+    let target = match to.join_app(app_id.clone()).await {
+        Ok(target) => target,
+        Err(Error::AppNotRunning) => {
+            let app = to.launch_app(app_id).await?;
+            MediaController::new(app, to.clone())?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let resumed = match session_state {
+        Some(session_state) => match target.resume_session(session_state).await {
+            Ok(_) => true,
+            Err(err) => {
+                warn!("Unable to resume session, loading media again: {}", err);
+                false
+            }
+        },
+        None => false,
+    };
+
+    if !resumed {
+        let media = status.media.clone().ok_or(Error::NoMediaSession)?;
+        target
+            .load_with(LoadRequestData {
+                active_track_ids: status.active_track_ids.clone(),
+                autoplay: Some(!matches!(status.player_state, PlayerState::Paused)),
+                current_time: Some(position),
+                media,
+                playback_rate: Some(status.playback_rate),
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    // Playback continues on the target, so a failure here doesn't undo the transfer
+    if let Err(err) = from.stop().await {
+        warn!("Unable to stop transferred session: {}", err);
+    }
+
+    Ok(target)
+}
@@ -0,0 +1,58 @@
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures_util::{Stream, StreamExt};
+use smol_timeout::TimeoutExt;
+
+use cast_sender::mock::MockReceiver;
+use cast_sender::namespace::media::{MediaInformation, MediaInformationBuilder, StreamType};
+use cast_sender::{Receiver, ReceiverConfig};
+
+/// Long enough for the mock, short enough to not stall the tests on failures
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn connect(config: ReceiverConfig) -> (MockReceiver, Receiver) {
+    let mock = MockReceiver::start().await.unwrap();
+    let receiver = Receiver::with_config(config);
+    receiver.connect(mock.addr()).await.unwrap();
+    (mock, receiver)
+}
+
+pub fn media(content_id: &str) -> MediaInformation {
+    MediaInformationBuilder::default()
+        .content_id(content_id)
+        .stream_type(StreamType::Buffered)
+        .content_type("audio/mpeg")
+        .duration(300.0)
+        .build()
+        .unwrap()
+}
+
+/// Waits for the next item which matches `predicate`
+pub async fn next_matching<T>(
+    stream: &mut (impl Stream<Item = T> + Unpin),
+    predicate: impl Fn(&T) -> bool,
+) -> T {
+    async {
+        while let Some(item) = stream.next().await {
+            if predicate(&item) {
+                return item;
+            }
+        }
+        panic!("stream ended");
+    }
+    .timeout(TIMEOUT)
+    .await
+    .expect("no matching item")
+}
+
+/// Waits until `condition` holds, e.g. for state which gets synced in the background
+pub async fn eventually<F: Future<Output = bool>>(condition: impl Fn() -> F) {
+    let start = Instant::now();
+    while !condition().await {
+        assert!(start.elapsed() < TIMEOUT, "condition never became true");
+        smol::Timer::after(Duration::from_millis(10)).await;
+    }
+}
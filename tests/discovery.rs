@@ -0,0 +1,98 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use futures_util::{Stream, StreamExt};
+use macro_rules_attribute::apply;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use smol_macros::test;
+use smol_timeout::TimeoutExt;
+
+use cast_sender::discovery::{DiscoveredDevice, Discovery, DiscoveryEvent, SERVICE_TYPE};
+use cast_sender::namespace::media::DeviceCapabilities;
+
+// Non-standard port so the test neither needs nor disturbs the system responder
+const MDNS_PORT: u16 = 5399;
+
+fn service(status: &str) -> ServiceInfo {
+    let properties = [
+        ("id", "0123456789abcdef"),
+        ("fn", "Living Room TV"),
+        ("md", "Chromecast"),
+        ("ca", "201221"),
+        ("rs", status),
+        ("ve", "05"),
+        ("ic", "/setup/icon.png"),
+    ];
+
+    ServiceInfo::new(
+        SERVICE_TYPE,
+        "Chromecast-0123456789abcdef",
+        "0123456789abcdef.local.",
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        8009,
+        &properties[..],
+    )
+    .unwrap()
+}
+
+async fn next_event(events: &mut (impl Stream<Item = DiscoveryEvent> + Unpin)) -> DiscoveryEvent {
+    events
+        .next()
+        .timeout(Duration::from_secs(10))
+        .await
+        .expect("no discovery event")
+        .expect("discovery stream ended")
+}
+
+fn assert_device(device: &DiscoveredDevice, status_text: Option<&str>) {
+    assert_eq!(
+        device.fullname,
+        format!("Chromecast-0123456789abcdef.{SERVICE_TYPE}")
+    );
+    assert_eq!(device.hostname, "0123456789abcdef.local.");
+    assert_eq!(device.addresses, [IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+    assert_eq!(device.port, 8009);
+    assert_eq!(
+        device.socket_addr(),
+        Some((Ipv4Addr::LOCALHOST, 8009).into())
+    );
+    assert_eq!(device.id, "0123456789abcdef");
+    assert_eq!(device.friendly_name, "Living Room TV");
+    assert_eq!(device.model_name, "Chromecast");
+    assert_eq!(
+        device.capabilities,
+        DeviceCapabilities::from_bits_retain(201221)
+    );
+    assert_eq!(device.status_text.as_deref(), status_text);
+    assert_eq!(device.version.as_deref(), Some("05"));
+    assert_eq!(device.icon_path.as_deref(), Some("/setup/icon.png"));
+}
+
+#[apply(test!)]
+async fn discovers_announced_receiver() {
+    let responder = ServiceDaemon::new_with_port(MDNS_PORT).unwrap();
+    let discovery = Discovery::with_port(MDNS_PORT).unwrap();
+    let mut events = Box::pin(discovery.browse().unwrap());
+
+    // An empty `rs` means the receiver is idle
+    responder.register(service("")).unwrap();
+    match next_event(&mut events).await {
+        DiscoveryEvent::Added(device) => assert_device(&device, None),
+        event => panic!("expected Added, got {event:?}"),
+    }
+
+    responder.register(service("YouTube")).unwrap();
+    match next_event(&mut events).await {
+        DiscoveryEvent::Updated(device) => assert_device(&device, Some("YouTube")),
+        event => panic!("expected Updated, got {event:?}"),
+    }
+
+    let fullname = service("").get_fullname().to_string();
+    responder.unregister(&fullname).unwrap();
+    match next_event(&mut events).await {
+        DiscoveryEvent::Removed(device) => assert_device(&device, Some("YouTube")),
+        event => panic!("expected Removed, got {event:?}"),
+    }
+
+    responder.shutdown().unwrap();
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use macro_rules_attribute::apply;
+use smol_macros::test;
+
+use cast_sender::mock::MockReceiver;
+use cast_sender::namespace::media::{
+    DetailedErrorCode, DeviceCapabilities, ErrorResponseData, Media, MediaCategory,
+    MediaInformation, PlayerState, ResponseData,
+};
+use cast_sender::{
+    AppId, CastAddr, Error, MediaController, MediaError, Receiver, ReceiverConfig, RequestErrorKind,
+};
+
+use common::{connect, media, next_matching};
+
+#[apply(test!)]
+async fn follows_media_session_of_other_sender() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app, receiver).unwrap();
+    media_controller.load(media("first.mp3")).await.unwrap();
+
+    let mut updates = media_controller.watch_status();
+
+    // Another sender started a new media session
+    let mut status = mock.media_status().await.unwrap();
+    status.media_session_id += 100;
+    mock.set_media_status(status.clone()).await;
+    next_matching(&mut updates, |update| {
+        update
+            .as_ref()
+            .is_some_and(|update| update.media_session_id == status.media_session_id)
+    })
+    .await;
+
+    // The mock rejects requests for any other than the current media session
+    media_controller.pause().await.unwrap();
+    let current = mock.media_status().await.unwrap();
+    assert_eq!(current.media_session_id, status.media_session_id);
+    assert!(matches!(current.player_state, PlayerState::Paused));
+}
+
+#[apply(test!)]
+async fn signals_end_of_media_session() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app.clone(), receiver).unwrap();
+    media_controller.load(media("track.mp3")).await.unwrap();
+    let mut updates = media_controller.watch_status();
+
+    // Receivers report an empty status once the media session is gone
+    let payload = Media::MediaStatus(ResponseData { status: vec![] });
+    mock.broadcast(&app.transport_id, payload).await;
+
+    next_matching(&mut updates, Option::is_none).await;
+    assert!(media_controller.status().await.is_none());
+}
+
+#[apply(test!)]
+async fn joins_running_app() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app.clone(), receiver).unwrap();
+    media_controller.load(media("track.mp3")).await.unwrap();
+
+    let other = Receiver::new();
+    other.connect(mock.addr()).await.unwrap();
+    let joined = other.join_session(&app.session_id).await.unwrap();
+    let status = joined.status().await.unwrap();
+    assert_eq!(status.media.unwrap().content_id, "track.mp3");
+
+    joined.pause().await.unwrap();
+    let status = mock.media_status().await.unwrap();
+    assert!(matches!(status.player_state, PlayerState::Paused));
+}
+
+#[apply(test!)]
+async fn joins_idle_app() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+
+    let other = Receiver::new();
+    other.connect(mock.addr()).await.unwrap();
+    let joined = other.join_app(AppId::DefaultMediaReceiver).await.unwrap();
+    assert!(joined.status().await.is_none());
+
+    let err = joined.pause().await.unwrap_err();
+    assert!(matches!(err, Error::NoMediaSession), "{err:?}");
+
+    joined.load(media("track.mp3")).await.unwrap();
+    joined.pause().await.unwrap();
+}
+
+#[apply(test!)]
+async fn keeps_unknown_detailed_error_code() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app.clone(), receiver).unwrap();
+
+    let load_failed = Media::LoadFailed(ErrorResponseData {
+        detailed_error_code: Some(DetailedErrorCode::Other(1234)),
+        ..Default::default()
+    });
+    mock.respond_next_with("LOAD", load_failed).await;
+
+    let err = media_controller.load(media("track.mp3")).await.unwrap_err();
+    let Error::Request(err) = err else {
+        panic!("expected a request error, got {err:?}");
+    };
+    let RequestErrorKind::MediaError(MediaError::LoadFailed(data)) = &err.kind else {
+        panic!("expected a load failure, got {:?}", err.kind);
+    };
+    let code = data.detailed_error_code.unwrap();
+    assert_eq!(code, DetailedErrorCode::Other(1234));
+    assert_eq!(err.transport_id(), app.transport_id);
+
+    // Serialized back unchanged, known codes keep their number too
+    assert_eq!(serde_json::to_value(code).unwrap(), 1234);
+    let known: DetailedErrorCode = serde_json::from_value(905.into()).unwrap();
+    assert_eq!(known, DetailedErrorCode::LoadFailed);
+    assert_eq!(serde_json::to_value(known).unwrap(), 905);
+}
+
+#[apply(test!)]
+async fn adjusts_playback() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app, receiver).unwrap();
+    media_controller.load(media("track.mp3")).await.unwrap();
+
+    let status = media_controller.seek(100.0, None).await.unwrap();
+    assert_eq!(status.current_time, 100.0);
+    let status = media_controller.seek_relative(-30.0, None).await.unwrap();
+    assert_eq!(status.current_time, 70.0);
+    assert!(matches!(status.player_state, PlayerState::Playing));
+
+    let status = media_controller.set_playback_rate(1.5).await.unwrap();
+    assert_eq!(status.playback_rate, 1.5);
+    let status = media_controller
+        .set_relative_playback_rate(2.0)
+        .await
+        .unwrap();
+    assert_eq!(status.playback_rate, 3.0);
+
+    let status = media_controller.set_volume(0.25).await.unwrap();
+    assert_eq!(status.volume.level, Some(0.25));
+    let status = media_controller.set_muted(true).await.unwrap();
+    assert_eq!(status.volume.muted, Some(true));
+    assert_eq!(status.volume.level, Some(0.25));
+
+    let status = media_controller
+        .set_active_tracks(vec![1, 2])
+        .await
+        .unwrap();
+    assert_eq!(status.active_track_ids, Some(vec![1, 2]));
+    let status = media_controller
+        .set_text_tracks_enabled(false, None)
+        .await
+        .unwrap();
+    assert_eq!(status.active_track_ids, Some(vec![]));
+
+    // The cached status follows the responses
+    let cached = media_controller.status().await.unwrap();
+    assert_eq!(cached.playback_rate, 3.0);
+    assert_eq!(mock.media_status().await.unwrap().current_time, 70.0);
+}
+
+#[apply(test!)]
+async fn rejects_videos_on_audio_only_receivers() {
+    let mock = MockReceiver::start().await.unwrap();
+    let receiver = Receiver::new();
+    let addr = CastAddr::from(mock.addr()).with_capabilities(DeviceCapabilities::AudioOut);
+    receiver.connect(addr).await.unwrap();
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app, receiver).unwrap();
+
+    let video = MediaInformation {
+        content_type: "video/mp4".to_string(),
+        ..media("movie.mp4")
+    };
+    let result = media_controller.load(video).await;
+    assert!(matches!(
+        result,
+        Err(Error::UnsupportedMediaCategory(MediaCategory::Video))
+    ));
+    assert!(mock.media_status().await.is_none());
+
+    media_controller.load(media("track.mp3")).await.unwrap();
+    let status = mock.media_status().await.unwrap();
+    assert_eq!(status.media.unwrap().content_id, "track.mp3");
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::time::Duration;
+
+use macro_rules_attribute::apply;
+use smol_macros::test;
+
+use cast_sender::namespace::media::DeviceCapabilities;
+use cast_sender::namespace::multizone::Device;
+use cast_sender::namespace::NamespaceUrn;
+use cast_sender::{Error, MultizoneController, ReceiverConfig, Volume};
+
+use common::{connect, next_matching};
+
+fn device(device_id: &str, name: &str) -> Device {
+    Device {
+        capabilities: DeviceCapabilities::AudioOut | DeviceCapabilities::MultizoneGroup,
+        device_id: device_id.into(),
+        name: name.into(),
+        volume: Volume {
+            control_type: None,
+            muted: Some(false),
+            level: Some(0.5),
+        },
+    }
+}
+
+#[apply(test!)]
+async fn tracks_group_members() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    mock.add_multizone_member(device("kitchen", "Kitchen speaker"))
+        .await;
+
+    let multizone = MultizoneController::new(receiver);
+    let members = multizone.refresh().await.unwrap();
+    assert_eq!(members, [device("kitchen", "Kitchen speaker")]);
+    assert!(multizone.casting_groups().await.unwrap().is_empty());
+
+    let mut updates = multizone.watch_members();
+    mock.add_multizone_member(device("bedroom", "Bedroom speaker"))
+        .await;
+    next_matching(&mut updates, |members| members.len() == 2).await;
+    assert_eq!(
+        multizone.member("bedroom").await,
+        Some(device("bedroom", "Bedroom speaker"))
+    );
+
+    mock.add_multizone_member(device("kitchen", "Kitchen"))
+        .await;
+    next_matching(&mut updates, |members| {
+        members.iter().any(|member| member.name == "Kitchen")
+    })
+    .await;
+
+    mock.remove_multizone_member("bedroom").await;
+    next_matching(&mut updates, |members| members.len() == 1).await;
+    assert_eq!(multizone.members().await, [device("kitchen", "Kitchen")]);
+}
+
+#[apply(test!)]
+async fn sets_member_volume() {
+    let config = ReceiverConfig {
+        request_timeout: Duration::from_millis(300),
+        ..Default::default()
+    };
+    let (mock, receiver) = connect(config).await;
+    mock.add_multizone_member(device("kitchen", "Kitchen speaker"))
+        .await;
+    mock.add_multizone_member(device("bedroom", "Bedroom speaker"))
+        .await;
+    let multizone = MultizoneController::new(receiver);
+    multizone.refresh().await.unwrap();
+
+    let updated = multizone
+        .set_member_volume("kitchen", 0.8, true)
+        .await
+        .unwrap();
+    assert_eq!(updated.volume.level, Some(0.8));
+    assert_eq!(updated.volume.muted, Some(true));
+    assert_eq!(multizone.member("kitchen").await, Some(updated));
+
+    // Other members keep their volume
+    let bedroom = multizone.member("bedroom").await.unwrap();
+    assert_eq!(bedroom.volume.level, Some(0.5));
+
+    // Unknown members never get announced
+    let err = multizone
+        .set_member_volume("garage", 0.8, false)
+        .await
+        .unwrap_err();
+    let Error::ResponseTimeout(context) = err else {
+        panic!("expected a timeout, got {err:?}");
+    };
+    assert_eq!(context.namespace, NamespaceUrn::Multizone);
+}
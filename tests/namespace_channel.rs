@@ -0,0 +1,158 @@
+mod common;
+
+use std::time::Duration;
+
+use futures_util::{future, StreamExt};
+use macro_rules_attribute::apply;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use smol_macros::test;
+use smol_timeout::TimeoutExt;
+
+use cast_sender::mock::MockReceiver;
+use cast_sender::namespace::{Custom, NamespaceUrn};
+use cast_sender::{App, AppId, Error, NamespaceChannel, Receiver, ReceiverConfig};
+
+use common::{connect, eventually, TIMEOUT};
+
+const NAMESPACE: &str = "urn:x-cast:com.example.game";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+enum Message {
+    Ping { value: i32 },
+    Slow { value: i32 },
+    Pong { value: i32 },
+    Score { points: i32 },
+}
+
+fn namespace() -> NamespaceUrn {
+    NAMESPACE.parse().unwrap()
+}
+
+fn custom(namespace: NamespaceUrn, message: impl serde::Serialize) -> Custom {
+    let Value::Object(fields) = serde_json::to_value(message).unwrap() else {
+        panic!("not an object");
+    };
+    Custom {
+        namespace,
+        fields: fields.into_iter().collect(),
+    }
+}
+
+async fn launch_game() -> (MockReceiver, Receiver, App) {
+    let config = ReceiverConfig {
+        heartbeat: None,
+        ..Default::default()
+    };
+    let (mock, receiver) = connect(config).await;
+    let app_id = AppId::Custom("GAME1234".into());
+    mock.add_app(app_id.clone(), vec![namespace()]).await;
+    let app = receiver.launch_app(app_id).await.unwrap();
+    (mock, receiver, app)
+}
+
+#[apply(test!)]
+async fn matches_responses_by_request_id() {
+    let (mock, receiver, app) = launch_game().await;
+    let channel = NamespaceChannel::<Message>::new(app, receiver, namespace()).unwrap();
+
+    // The response to the first request arrives last
+    mock.set_delay("SLOW", Duration::from_millis(200)).await;
+    mock.respond_next_with("SLOW", custom(namespace(), Message::Pong { value: 1 }))
+        .await;
+    mock.respond_next_with("PING", custom(namespace(), Message::Pong { value: 2 }))
+        .await;
+
+    let (slow, ping) = future::join(
+        channel.request(Message::Slow { value: 1 }),
+        channel.request(Message::Ping { value: 2 }),
+    )
+    .await;
+    assert_eq!(slow.unwrap(), Message::Pong { value: 1 });
+    assert_eq!(ping.unwrap(), Message::Pong { value: 2 });
+
+    // Unanswered requests time out instead of taking unrelated messages
+    let channel = channel.with_timeout(Duration::from_millis(200));
+    mock.ignore_next("PING").await;
+    let request = channel.request(Message::Ping { value: 3 });
+    let broadcast = mock.broadcast(
+        &channel.app().transport_id,
+        custom(namespace(), Message::Pong { value: 3 }),
+    );
+    let (result, ()) = future::join(request, broadcast).await;
+    assert!(
+        matches!(result, Err(Error::ResponseTimeout(_))),
+        "{result:?}"
+    );
+}
+
+#[apply(test!)]
+async fn filters_messages() {
+    let (mock, receiver, app) = launch_game().await;
+    let channel = NamespaceChannel::<Message>::new(app.clone(), receiver, namespace()).unwrap();
+    let mut messages = channel.messages();
+
+    let other_namespace: NamespaceUrn = "urn:x-cast:com.example.other".parse().unwrap();
+    mock.broadcast(
+        &app.transport_id,
+        custom(other_namespace, Message::Score { points: 1 }),
+    )
+    .await;
+    mock.broadcast(
+        "other-app",
+        custom(namespace(), Message::Score { points: 2 }),
+    )
+    .await;
+    mock.broadcast(
+        &app.transport_id,
+        custom(namespace(), json!({ "type": "UNKNOWN" })),
+    )
+    .await;
+    mock.broadcast(
+        &app.transport_id,
+        custom(namespace(), Message::Score { points: 3 }),
+    )
+    .await;
+
+    // Messages arrive in order, so all others got skipped
+    let message = messages.next().timeout(TIMEOUT).await.flatten();
+    assert_eq!(message, Some(Message::Score { points: 3 }));
+}
+
+#[apply(test!)]
+async fn rejects_unsupported_namespaces() {
+    let (_mock, receiver, app) = launch_game().await;
+
+    // Built-in namespaces have their own controllers, even if the app supports them
+    let mut media_app = app.clone();
+    media_app.namespaces.push(NamespaceUrn::Media);
+    let result = NamespaceChannel::<Message>::new(media_app, receiver.clone(), NamespaceUrn::Media);
+    assert!(matches!(result, Err(Error::UnsupportedNamespace)));
+
+    let other_namespace = "urn:x-cast:com.example.other".parse().unwrap();
+    let result = NamespaceChannel::<Message>::new(app, receiver, other_namespace);
+    assert!(matches!(result, Err(Error::UnsupportedNamespace)));
+}
+
+#[apply(test!)]
+async fn rejects_messages_which_are_not_objects() {
+    let (mock, receiver, app) = launch_game().await;
+    let channel = NamespaceChannel::<Value>::new(app, receiver, namespace()).unwrap();
+
+    let result = channel.send(json!(["not", "an", "object"])).await;
+    assert!(matches!(result, Err(Error::Protocol(_))), "{result:?}");
+    let result = channel.request(json!(42)).await;
+    assert!(matches!(result, Err(Error::Protocol(_))), "{result:?}");
+
+    // Only the valid message got sent
+    channel.send(json!({ "type": "PING" })).await.unwrap();
+    eventually(|| async {
+        let requests = mock.received().await;
+        let sent = requests
+            .iter()
+            .filter(|request| request.namespace == namespace());
+        sent.count() == 1
+    })
+    .await;
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use macro_rules_attribute::apply;
+use smol_macros::test;
+
+use cast_sender::namespace::connection::Connection;
+use cast_sender::namespace::media::{QueueItem, RepeatMode};
+use cast_sender::{AppId, MediaController, Payload, QueueController, Receiver, ReceiverConfig};
+
+use common::{connect, eventually, media};
+
+fn item(content_id: &str) -> QueueItem {
+    QueueItem {
+        media: Some(media(content_id)),
+        ..Default::default()
+    }
+}
+
+async fn content_ids(queue: &QueueController) -> Vec<String> {
+    queue
+        .items()
+        .await
+        .into_iter()
+        .map(|item| item.media.map(|media| media.content_id).unwrap_or_default())
+        .collect()
+}
+
+#[apply(test!)]
+async fn manages_queue() {
+    let (_mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let queue = QueueController::new(MediaController::new(app, receiver).unwrap());
+
+    let items = vec![item("a.mp3"), item("b.mp3"), item("c.mp3")];
+    queue
+        .load(items, None, Some(RepeatMode::RepeatAll))
+        .await
+        .unwrap();
+    assert_eq!(content_ids(&queue).await, ["a.mp3", "b.mp3", "c.mp3"]);
+    let ids = queue.item_ids().await;
+    assert_eq!(queue.current_item_id().await, Some(ids[0]));
+    assert!(matches!(queue.repeat_mode().await, RepeatMode::RepeatAll));
+
+    queue
+        .insert(vec![item("d.mp3")], Some(ids[1]))
+        .await
+        .unwrap();
+    eventually(|| async { content_ids(&queue).await == ["a.mp3", "d.mp3", "b.mp3", "c.mp3"] })
+        .await;
+
+    let status = queue.next().await.unwrap();
+    let inserted = queue.item_ids().await[1];
+    assert_eq!(status.current_item_id, Some(inserted));
+    assert_eq!(queue.current_item_id().await, Some(inserted));
+
+    queue.reorder(vec![ids[0]], None).await.unwrap();
+    eventually(|| async { content_ids(&queue).await == ["d.mp3", "b.mp3", "c.mp3", "a.mp3"] })
+        .await;
+
+    queue.remove(vec![inserted]).await.unwrap();
+    eventually(|| async { content_ids(&queue).await == ["b.mp3", "c.mp3", "a.mp3"] }).await;
+}
+
+#[apply(test!)]
+async fn syncs_changes_of_other_senders() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let queue = QueueController::new(MediaController::new(app.clone(), receiver).unwrap());
+    queue
+        .load(vec![item("a.mp3"), item("b.mp3")], None, None)
+        .await
+        .unwrap();
+
+    let other = Receiver::new();
+    other.connect(mock.addr()).await.unwrap();
+    let other_queue = QueueController::new(other.join_session(&app.session_id).await.unwrap());
+    other_queue.insert(vec![item("c.mp3")], None).await.unwrap();
+
+    eventually(|| async { content_ids(&queue).await == ["a.mp3", "b.mp3", "c.mp3"] }).await;
+}
+
+#[apply(test!)]
+async fn releases_sender_once_dropped() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let other = receiver.new_sender().await.unwrap();
+    let sender_id = other.sender_id().to_string();
+    let queue = QueueController::new(other.join_session(&app.session_id).await.unwrap());
+    queue.load(vec![item("a.mp3")], None, None).await.unwrap();
+
+    // The background sync must not keep the sender id alive
+    drop(other);
+    drop(queue);
+    eventually(|| async {
+        mock.received().await.iter().any(|request| {
+            request.source_id == sender_id
+                && request.destination_id == app.transport_id
+                && matches!(request.payload, Payload::Connection(Connection::Close))
+        })
+    })
+    .await;
+}
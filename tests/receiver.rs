@@ -0,0 +1,405 @@
+mod common;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures_util::{Stream, StreamExt};
+use macro_rules_attribute::apply;
+use smol_macros::test;
+use smol_timeout::TimeoutExt;
+
+use cast_sender::mock::MockReceiver;
+use cast_sender::namespace::connection::Connection;
+use cast_sender::namespace::media::{PlayerState, SeekResumeState};
+use cast_sender::namespace::multizone::{DeviceRemovedResponse, Multizone};
+use cast_sender::namespace::receiver::Availability;
+use cast_sender::namespace::{Custom, NamespaceUrn};
+use cast_sender::{
+    AppEvent, AppId, ConnectionState, DisconnectReason, Error, HeartbeatConfig, MediaController,
+    Payload, ReceiverConfig, ReconnectPolicy, RequestErrorKind, Response,
+};
+
+use common::{connect, eventually, media, next_matching, TIMEOUT};
+
+#[apply(test!)]
+async fn launches_app() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    assert_eq!(app.app_id, AppId::DefaultMediaReceiver);
+    assert!(app.namespaces.contains(&NamespaceUrn::Media));
+    let session_ids = |apps: Vec<cast_sender::App>| -> Vec<String> {
+        apps.into_iter().map(|app| app.session_id).collect()
+    };
+    assert_eq!(session_ids(mock.running_apps().await), [&*app.session_id]);
+    assert_eq!(
+        session_ids(receiver.applications().await.unwrap()),
+        [&*app.session_id]
+    );
+
+    // The virtual connection to the app got established
+    let connected = mock.received().await.into_iter().any(|request| {
+        request.destination_id == app.transport_id
+            && matches!(request.payload, Payload::Connection(Connection::Connect))
+    });
+    assert!(connected);
+}
+
+#[apply(test!)]
+async fn reports_launch_error() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+
+    let err = receiver
+        .launch_app(AppId::Custom("DEADBEEF".into()))
+        .await
+        .unwrap_err();
+    let Error::Request(err) = err else {
+        panic!("expected a request error, got {err:?}");
+    };
+    let RequestErrorKind::LaunchError(launch_error) = &err.kind else {
+        panic!("expected a launch error, got {:?}", err.kind);
+    };
+    assert_eq!(launch_error.reason, "NOT_FOUND");
+    assert_eq!(err.namespace(), &NamespaceUrn::Receiver);
+    assert!(mock.running_apps().await.is_empty());
+}
+
+#[apply(test!)]
+async fn controls_media() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app, receiver).unwrap();
+
+    media_controller.load(media("track.mp3")).await.unwrap();
+    let status = mock.media_status().await.unwrap();
+    assert_eq!(status.media.unwrap().content_id, "track.mp3");
+    assert!(matches!(status.player_state, PlayerState::Playing));
+
+    media_controller.pause().await.unwrap();
+    let status = mock.media_status().await.unwrap();
+    assert!(matches!(status.player_state, PlayerState::Paused));
+
+    let status = media_controller
+        .seek(42.0, Some(SeekResumeState::PlaybackStart))
+        .await
+        .unwrap();
+    assert_eq!(status.current_time, 42.0);
+    assert!(matches!(status.player_state, PlayerState::Playing));
+    assert_eq!(mock.media_status().await.unwrap().current_time, 42.0);
+}
+
+#[apply(test!)]
+async fn times_out_without_response() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+
+    mock.ignore_next("GET_STATUS").await;
+    let timeout = Duration::from_millis(200);
+    let platform = cast_sender::App::receiver();
+    let err = receiver
+        .send_request_with_timeout(
+            &platform,
+            cast_sender::namespace::receiver::Receiver::GetStatus,
+            timeout,
+        )
+        .await
+        .unwrap_err();
+    let Error::ResponseTimeout(context) = err else {
+        panic!("expected a timeout, got {err:?}");
+    };
+    assert_eq!(context.namespace, NamespaceUrn::Receiver);
+    assert_eq!(context.destination_id, platform.transport_id);
+    assert!(context.request_id.is_some());
+
+    // Only the ignored request is affected
+    receiver.status().await.unwrap();
+}
+
+#[apply(test!)]
+async fn detects_unresponsive_receiver() {
+    let heartbeat = HeartbeatConfig {
+        interval: Duration::from_millis(100),
+        timeout: Duration::from_millis(500),
+    };
+    let config = ReceiverConfig {
+        heartbeat: Some(heartbeat),
+        ..Default::default()
+    };
+    let (mock, receiver) = connect(config).await;
+    let mut states = receiver.connection_events();
+
+    mock.set_unresponsive(true).await;
+    let err = receiver.status().await.unwrap_err();
+    assert!(matches!(err, Error::HeartbeatTimeout), "{err:?}");
+
+    next_matching(&mut states, |state| {
+        *state == ConnectionState::Disconnected(DisconnectReason::HeartbeatTimeout)
+    })
+    .await;
+    assert!(!receiver.is_connected().await);
+}
+
+#[apply(test!)]
+async fn reconnects_after_connection_loss() {
+    let reconnect_policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        max_attempts: Some(5),
+    };
+    let config = ReceiverConfig {
+        reconnect_policy: Some(reconnect_policy),
+        ..Default::default()
+    };
+    let (mock, receiver) = connect(config).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let mut states = receiver.connection_events();
+
+    // Requests are sent in order, so the mock has seen the connections once it responded
+    receiver.status().await.unwrap();
+    mock.drop_connections().await;
+    next_matching(&mut states, |state| {
+        matches!(state, ConnectionState::Reconnecting { .. })
+    })
+    .await;
+    next_matching(&mut states, |state| *state == ConnectionState::Connected).await;
+
+    receiver.status().await.unwrap();
+
+    // The virtual connection to the app got restored on the new connection
+    let connects = mock
+        .received()
+        .await
+        .into_iter()
+        .filter(|request| {
+            request.destination_id == app.transport_id
+                && matches!(request.payload, Payload::Connection(Connection::Connect))
+        })
+        .count();
+    assert_eq!(connects, 2);
+}
+
+#[apply(test!)]
+async fn notices_app_stopped_elsewhere() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app.clone(), receiver.clone()).unwrap();
+    let mut app_events = receiver.app_events();
+
+    mock.stop_app(&app).await;
+    let event = app_events
+        .next()
+        .timeout(TIMEOUT)
+        .await
+        .expect("no app event")
+        .unwrap();
+    let AppEvent::AppClosed(closed) = event;
+    assert_eq!(closed.transport_id, app.transport_id);
+    assert!(receiver.is_app_closed(&app).await);
+
+    let err = media_controller.load(media("track.mp3")).await.unwrap_err();
+    assert!(matches!(err, Error::AppNotRunning), "{err:?}");
+}
+
+#[apply(test!)]
+async fn tracks_closed_connections_per_sender() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let other = receiver.new_sender().await.unwrap();
+    let other_controller = other.join_session(&app.session_id).await.unwrap();
+    let mut app_events = receiver.app_events();
+    let mut other_app_events = other.app_events();
+
+    mock.close_connection(&app, other.sender_id()).await;
+    let AppEvent::AppClosed(closed) = other_app_events
+        .next()
+        .timeout(TIMEOUT)
+        .await
+        .expect("no app event")
+        .unwrap();
+    assert_eq!(closed.transport_id, app.transport_id);
+    assert!(other.is_app_closed(&app).await);
+    let err = other_controller.load(media("track.mp3")).await.unwrap_err();
+    assert!(matches!(err, Error::AppNotRunning), "{err:?}");
+
+    // The primary sender id is still connected to the app
+    assert!(!receiver.is_app_closed(&app).await);
+    let media_controller = MediaController::new(app.clone(), receiver.clone()).unwrap();
+    media_controller.load(media("track.mp3")).await.unwrap();
+    assert!(app_events.next().timeout(TIMEOUT / 10).await.is_none());
+
+    // Stopping the app closes the connections of all sender ids
+    mock.stop_app(&app).await;
+    app_events
+        .next()
+        .timeout(TIMEOUT)
+        .await
+        .expect("no app event");
+    assert!(receiver.is_app_closed(&app).await);
+}
+
+#[apply(test!)]
+async fn reports_app_availability() {
+    let (_mock, receiver) = connect(ReceiverConfig::default()).await;
+
+    let unknown = AppId::Custom("DEADBEEF".into());
+    let availability = receiver
+        .app_availability(&[AppId::DefaultMediaReceiver, unknown.clone()])
+        .await
+        .unwrap();
+    assert_eq!(availability.len(), 2);
+    assert_eq!(
+        availability[&AppId::DefaultMediaReceiver],
+        Availability::AppAvailable
+    );
+    assert_eq!(availability[&unknown], Availability::AppUnavailable);
+}
+
+// Marker of the custom test messages, or the namespace of others
+fn event_kind(response: &Response) -> Option<String> {
+    match &response.payload {
+        Payload::Custom(custom) => custom.fields.get("type")?.as_str().map(str::to_string),
+        Payload::Multizone(_) => Some("multizone".into()),
+        _ => None,
+    }
+}
+
+fn marker(kind: &str) -> Custom {
+    Custom {
+        namespace: "urn:x-cast:com.example.test".parse().unwrap(),
+        fields: HashMap::from([("type".into(), kind.into())]),
+    }
+}
+
+async fn next_kinds(
+    events: &mut (impl Stream<Item = Response> + Unpin),
+    count: usize,
+) -> Vec<String> {
+    let mut kinds = Vec::new();
+    while kinds.len() < count {
+        let response = next_matching(events, |response| event_kind(response).is_some()).await;
+        kinds.extend(event_kind(&response));
+    }
+    kinds
+}
+
+#[apply(test!)]
+async fn filters_events() {
+    let config = ReceiverConfig {
+        heartbeat: None,
+        ..Default::default()
+    };
+    let (mock, receiver) = connect(config).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let other = receiver.new_sender().await.unwrap();
+
+    let mut events = receiver.events();
+    let mut multizone_events = receiver.namespace_events(NamespaceUrn::Multizone);
+    let mut app_events = receiver.transport_events(&app.transport_id);
+    let mut other_events = other.events();
+    let mut other_app_events = other.transport_events(&app.transport_id);
+
+    mock.send_to(&app.transport_id, other.sender_id(), marker("to other"))
+        .await;
+    mock.broadcast(&app.transport_id, marker("to all")).await;
+    let removed = DeviceRemovedResponse {
+        device_id: "kitchen".into(),
+    };
+    mock.broadcast("receiver-0", Multizone::DeviceRemoved(removed))
+        .await;
+
+    // Messages arrive in order, so anything skipped before the last one wasn't delivered
+    assert_eq!(next_kinds(&mut events, 2).await, ["to all", "multizone"]);
+    assert_eq!(next_kinds(&mut multizone_events, 1).await, ["multizone"]);
+    assert_eq!(next_kinds(&mut app_events, 1).await, ["to all"]);
+    assert_eq!(
+        next_kinds(&mut other_events, 3).await,
+        ["to other", "to all", "multizone"]
+    );
+    assert_eq!(
+        next_kinds(&mut other_app_events, 2).await,
+        ["to other", "to all"]
+    );
+}
+
+#[apply(test!)]
+async fn delivers_malformed_payloads_as_unknown() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let mut events = receiver.namespace_events(NamespaceUrn::Receiver);
+
+    for raw_json in ["{not json", r#"{"type":"RECEIVER_STATUS","status":42}"#] {
+        let payload = Payload::Unknown {
+            namespace: NamespaceUrn::Receiver,
+            raw_json: raw_json.into(),
+        };
+        mock.broadcast("receiver-0", payload).await;
+
+        let response = next_matching(&mut events, |response| {
+            matches!(response.payload, Payload::Unknown { .. })
+        })
+        .await;
+        let Payload::Unknown {
+            namespace,
+            raw_json: received,
+        } = response.payload
+        else {
+            unreachable!();
+        };
+        assert_eq!(namespace, NamespaceUrn::Receiver);
+        assert_eq!(received, raw_json);
+    }
+
+    // The connection is still usable
+    assert!(receiver.is_connected().await);
+    receiver.status().await.unwrap();
+}
+
+async fn closes(mock: &MockReceiver, sender_id: &str, transport_id: &str) -> bool {
+    mock.received().await.iter().any(|request| {
+        request.source_id == sender_id
+            && request.destination_id == transport_id
+            && matches!(request.payload, Payload::Connection(Connection::Close))
+    })
+}
+
+#[apply(test!)]
+async fn closes_connections_of_dropped_sender() {
+    let (mock, receiver) = connect(ReceiverConfig::default()).await;
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let other = receiver.new_sender().await.unwrap();
+    let sender_id = other.sender_id().to_string();
+    let media_controller = other.join_session(&app.session_id).await.unwrap();
+
+    // Only dropping the last handle closes the connections
+    drop(other);
+    media_controller.load(media("track.mp3")).await.unwrap();
+    assert!(!closes(&mock, &sender_id, &app.transport_id).await);
+
+    drop(media_controller);
+    eventually(|| closes(&mock, &sender_id, &app.transport_id)).await;
+    eventually(|| closes(&mock, &sender_id, "receiver-0")).await;
+
+    // The primary sender id keeps its connections
+    assert!(!closes(&mock, receiver.sender_id(), &app.transport_id).await);
+    let media_controller = MediaController::new(app, receiver).unwrap();
+    media_controller.pause().await.unwrap();
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use macro_rules_attribute::apply;
+use smol_macros::test;
+
+use cast_sender::namespace::media::{ErrorReason, ErrorResponseData, Media, PlayerState};
+use cast_sender::namespace::receiver;
+use cast_sender::namespace::NamespaceUrn;
+use cast_sender::{transfer_session, AppId, MediaController, Payload, Receiver, ReceiverConfig};
+
+use common::{connect, media};
+
+async fn playing_controller(receiver: &Receiver) -> MediaController {
+    let app = receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let media_controller = MediaController::new(app, receiver.clone()).unwrap();
+    media_controller.load(media("track.mp3")).await.unwrap();
+    media_controller.seek(42.0, None).await.unwrap();
+    media_controller.pause().await.unwrap();
+    media_controller
+}
+
+#[apply(test!)]
+async fn transfers_stored_session() {
+    let (from_mock, from_receiver) = connect(ReceiverConfig::default()).await;
+    let (to_mock, to_receiver) = connect(ReceiverConfig::default()).await;
+    let from = playing_controller(&from_receiver).await;
+
+    let target = transfer_session(&from, &to_receiver).await.unwrap();
+    assert_eq!(target.app().app_id, AppId::DefaultMediaReceiver);
+
+    let status = to_mock.media_status().await.unwrap();
+    assert_eq!(status.media.unwrap().content_id, "track.mp3");
+    assert_eq!(status.current_time, 42.0);
+    assert!(matches!(status.player_state, PlayerState::Paused));
+    assert!(target.status().await.is_some());
+
+    // The session got resumed, instead of loading the media again
+    let requests = to_mock.received().await;
+    let resumed = requests
+        .iter()
+        .any(|request| matches!(request.payload, Payload::Media(Media::ResumeSession(_))));
+    assert!(resumed);
+
+    // The source got stopped
+    assert!(from_mock.media_status().await.is_none());
+}
+
+#[apply(test!)]
+async fn reloads_media_without_stored_session() {
+    let (from_mock, from_receiver) = connect(ReceiverConfig::default()).await;
+    let (to_mock, to_receiver) = connect(ReceiverConfig::default()).await;
+    let from = playing_controller(&from_receiver).await;
+
+    // The target app is already running and gets joined
+    to_receiver
+        .launch_app(AppId::DefaultMediaReceiver)
+        .await
+        .unwrap();
+    let running = to_mock.running_apps().await;
+
+    let store_failed = Media::InvalidRequest(ErrorResponseData {
+        reason: ErrorReason::InvalidCommand,
+        ..Default::default()
+    });
+    from_mock
+        .respond_next_with("STORE_SESSION", store_failed)
+        .await;
+
+    let target = transfer_session(&from, &to_receiver).await.unwrap();
+    assert_eq!(target.app().session_id, running[0].session_id);
+
+    let status = to_mock.media_status().await.unwrap();
+    assert_eq!(status.media.unwrap().content_id, "track.mp3");
+    assert_eq!(status.current_time, 42.0);
+    assert!(matches!(status.player_state, PlayerState::Paused));
+    assert!(from_mock.media_status().await.is_none());
+
+    // Only a single app got launched on the target
+    let launches = to_mock
+        .received()
+        .await
+        .into_iter()
+        .filter(|request| {
+            request.namespace == NamespaceUrn::Receiver
+                && matches!(
+                    request.payload,
+                    Payload::Receiver(receiver::Receiver::Launch(_))
+                )
+        })
+        .count();
+    assert_eq!(launches, 1);
+}